@@ -0,0 +1,188 @@
+use crate::vfx::video::Pixel;
+
+/// How the colours of an overlay are combined with the colours underneath it, like the layer modes of an image editor.
+/// The modes follow the W3C Compositing and Blending specification, so they look the same as in CSS, Photoshop or GIMP.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    #[default]
+    Normal,     // The overlay covers what is underneath
+    Multiply,   // Multiplies the colours, which always darkens
+    Screen,     // Multiplies the inverted colours, which always lightens
+    Overlay,    // Multiplies dark areas and screens light areas of the underlying frame
+    SoftLight,  // A gentler `Overlay`, like shining a diffused light on the frame
+    HardLight,  // Multiplies or screens depending on the overlay, like shining a harsh light on the frame
+    Add,        // Adds the colours together, also known as linear dodge
+    Subtract,   // Subtracts the overlay from the colours underneath
+    Difference, // The absolute difference between the colours
+    Darken,     // Keeps the darker of the two colours, per channel
+    Lighten,    // Keeps the lighter of the two colours, per channel
+    ColorDodge, // Brightens the frame to reflect the overlay
+    ColorBurn,  // Darkens the frame to reflect the overlay
+    Hue,        // The hue of the overlay with the saturation and luminosity underneath
+    Saturation, // The saturation of the overlay with the hue and luminosity underneath
+    Color,      // The hue and saturation of the overlay with the luminosity underneath
+    Luminosity, // The luminosity of the overlay with the hue and saturation underneath
+}
+
+impl BlendMode {
+    /// Composites one pixel over another with this blend mode. Where the lower pixel is transparent, the overlay is drawn as it is.
+    ///
+    /// # Arguments
+    ///
+    /// * `over` - The pixel of the overlay.
+    /// * `under` - The pixel underneath it.
+    /// * `opacity` - How much of the overlay to use, from 0 (none) to 1 (all of its alpha).
+    ///
+    /// # Returns
+    ///
+    /// The blended pixel.
+    pub fn composite(self, over: Pixel, under: Pixel, opacity: f32) -> Pixel {
+        if self == BlendMode::Normal || under.a == 0 {
+            return over.over(under, opacity);
+        }
+
+        let source = to_unit(over);
+        let backdrop = to_unit(under);
+        let blended = self.blend(backdrop, source);
+
+        // Only as much of the blended colour shows as the lower layer covers
+        let coverage = under.a as f32 / 255.0;
+        let mix = |i: usize| to_byte(source[i] + (blended[i] - source[i]) * coverage);
+        Pixel::rgba(mix(0), mix(1), mix(2), over.a).over(under, opacity)
+    }
+
+    /// Private function; blends two colours with channels from 0 to 1, ignoring alpha.
+    fn blend(self, backdrop: [f32; 3], source: [f32; 3]) -> [f32; 3] {
+        let separable = |function: fn(f32, f32) -> f32| {
+            [
+                function(backdrop[0], source[0]),
+                function(backdrop[1], source[1]),
+                function(backdrop[2], source[2]),
+            ]
+        };
+
+        match self {
+            BlendMode::Normal => source,
+            BlendMode::Multiply => separable(|b, s| b * s),
+            BlendMode::Screen => separable(screen),
+            BlendMode::Overlay => separable(|b, s| hard_light(s, b)),
+            BlendMode::SoftLight => separable(soft_light),
+            BlendMode::HardLight => separable(hard_light),
+            BlendMode::Add => separable(|b, s| (b + s).min(1.0)),
+            BlendMode::Subtract => separable(|b, s| (b - s).max(0.0)),
+            BlendMode::Difference => separable(|b, s| (b - s).abs()),
+            BlendMode::Darken => separable(f32::min),
+            BlendMode::Lighten => separable(f32::max),
+            BlendMode::ColorDodge => separable(color_dodge),
+            BlendMode::ColorBurn => separable(color_burn),
+            BlendMode::Hue => set_lum(set_sat(source, sat(backdrop)), lum(backdrop)),
+            BlendMode::Saturation => set_lum(set_sat(backdrop, sat(source)), lum(backdrop)),
+            BlendMode::Color => set_lum(source, lum(backdrop)),
+            BlendMode::Luminosity => set_lum(backdrop, lum(source)),
+        }
+    }
+}
+
+/// Private function; converts a pixel's colour to channels from 0 to 1.
+fn to_unit(pixel: Pixel) -> [f32; 3] {
+    [pixel.r as f32 / 255.0, pixel.g as f32 / 255.0, pixel.b as f32 / 255.0]
+}
+
+/// Private function; converts a channel from 0 to 1 back to a byte.
+fn to_byte(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Private function; the screen blend of one channel.
+fn screen(backdrop: f32, source: f32) -> f32 {
+    backdrop + source - backdrop * source
+}
+
+/// Private function; the hard light blend of one channel.
+fn hard_light(backdrop: f32, source: f32) -> f32 {
+    if source <= 0.5 {
+        backdrop * source * 2.0
+    } else {
+        screen(backdrop, source * 2.0 - 1.0)
+    }
+}
+
+/// Private function; the soft light blend of one channel.
+fn soft_light(backdrop: f32, source: f32) -> f32 {
+    if source <= 0.5 {
+        backdrop - (1.0 - 2.0 * source) * backdrop * (1.0 - backdrop)
+    } else {
+        let d = if backdrop <= 0.25 {
+            ((16.0 * backdrop - 12.0) * backdrop + 4.0) * backdrop
+        } else {
+            backdrop.sqrt()
+        };
+        backdrop + (2.0 * source - 1.0) * (d - backdrop)
+    }
+}
+
+/// Private function; the colour dodge blend of one channel.
+fn color_dodge(backdrop: f32, source: f32) -> f32 {
+    if backdrop <= 0.0 {
+        0.0
+    } else if source >= 1.0 {
+        1.0
+    } else {
+        (backdrop / (1.0 - source)).min(1.0)
+    }
+}
+
+/// Private function; the colour burn blend of one channel.
+fn color_burn(backdrop: f32, source: f32) -> f32 {
+    if backdrop >= 1.0 {
+        1.0
+    } else if source <= 0.0 {
+        0.0
+    } else {
+        1.0 - ((1.0 - backdrop) / source).min(1.0)
+    }
+}
+
+/// Private function; the luminosity of a colour, as the blending specification defines it.
+fn lum(color: [f32; 3]) -> f32 {
+    0.3 * color[0] + 0.59 * color[1] + 0.11 * color[2]
+}
+
+/// Private function; the saturation of a colour, which is the range of its channels.
+fn sat(color: [f32; 3]) -> f32 {
+    color[0].max(color[1]).max(color[2]) - color[0].min(color[1]).min(color[2])
+}
+
+/// Private function; gives a colour a new luminosity, keeping its hue and as much of its saturation as fits.
+fn set_lum(color: [f32; 3], luminosity: f32) -> [f32; 3] {
+    let shift = luminosity - lum(color);
+    clip_color(color.map(|channel| channel + shift))
+}
+
+/// Private function; pulls channels that left the 0 to 1 range back in, without changing the luminosity.
+fn clip_color(color: [f32; 3]) -> [f32; 3] {
+    let l = lum(color);
+    let min = color[0].min(color[1]).min(color[2]);
+    let max = color[0].max(color[1]).max(color[2]);
+
+    let mut color = color;
+    if min < 0.0 {
+        color = color.map(|channel| l + (channel - l) * l / (l - min));
+    }
+    if max > 1.0 {
+        color = color.map(|channel| l + (channel - l) * (1.0 - l) / (max - l));
+    }
+    color
+}
+
+/// Private function; gives a colour a new saturation, keeping its hue.
+fn set_sat(color: [f32; 3], saturation: f32) -> [f32; 3] {
+    let min = color[0].min(color[1]).min(color[2]);
+    let max = color[0].max(color[1]).max(color[2]);
+
+    if max > min {
+        color.map(|channel| (channel - min) * saturation / (max - min))
+    } else {
+        [0.0; 3]
+    }
+}
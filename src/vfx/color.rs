@@ -0,0 +1,492 @@
+use crate::vfx::resize::{linear_to_srgb, srgb_to_linear};
+use crate::vfx::video::Pixel;
+use rayon::prelude::*;
+use std::fmt;
+
+/// The luma weights of Rec. 709, which HD video uses for how bright each channel looks.
+pub(crate) const LUMA: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+/// How a colour is turned into a single brightness for greyscale.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Luma {
+    Average, // Averages the channels equally, which makes greens look too dark
+    Rec601,  // The weights of standard definition video and JPEG
+    #[default]
+    Rec709,  // The weights of HD video, which matches what most editors show
+}
+
+impl Luma {
+    /// Returns how much the red, green and blue channels count towards the brightness. They add up to one.
+    pub fn weights(self) -> [f32; 3] {
+        match self {
+            Luma::Average => [1.0 / 3.0; 3],
+            Luma::Rec601 => [0.299, 0.587, 0.114],
+            Luma::Rec709 => LUMA,
+        }
+    }
+}
+
+/// Which colour channels an adjustment changes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Channel {
+    #[default]
+    Rgb,   // All three channels the same way
+    Red,   // Only the red channel
+    Green, // Only the green channel
+    Blue,  // Only the blue channel
+}
+
+impl Channel {
+    /// Private function; returns whether the channel at `index`, with 0 being red, is changed.
+    fn includes(self, index: usize) -> bool {
+        match self {
+            Channel::Rgb => true,
+            Channel::Red => index == 0,
+            Channel::Green => index == 1,
+            Channel::Blue => index == 2,
+        }
+    }
+}
+
+/// Input and output levels, like the Levels dialog of an image editor. Values are from 0 to 255.
+/// The input black and white points are stretched to the output black and white points, with `gamma` bending the midtones.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Levels {
+    pub input_black: u8,  // This and everything darker becomes `output_black`
+    pub input_white: u8,  // This and everything brighter becomes `output_white`
+    pub gamma: f32,       // Brightens the midtones above 1 and darkens them below 1
+    pub output_black: u8, // The darkest value left
+    pub output_white: u8, // The brightest value left
+}
+
+impl Default for Levels {
+    /// Levels that change nothing.
+    fn default() -> Self {
+        Levels {
+            input_black: 0,
+            input_white: 255,
+            gamma: 1.0,
+            output_black: 0,
+            output_white: 255,
+        }
+    }
+}
+
+impl Levels {
+    /// Creates levels that stretch `input_black` to `input_white` over the full range.
+    pub fn new(input_black: u8, input_white: u8) -> Levels {
+        Levels {
+            input_black,
+            input_white,
+            ..Levels::default()
+        }
+    }
+
+    /// Sets the midtone gamma, where values above 1 brighten the midtones.
+    pub fn gamma(mut self, gamma: f32) -> Levels {
+        self.gamma = gamma;
+        self
+    }
+
+    /// Sets the darkest and brightest values that are left, such as `output(16, 235)` for a faded look.
+    pub fn output(mut self, black: u8, white: u8) -> Levels {
+        self.output_black = black;
+        self.output_white = white;
+        self
+    }
+
+    /// Private function; maps one channel from 0 to 1.
+    fn apply(&self, value: f32) -> f32 {
+        let (black, white) = (self.input_black as f32 / 255.0, self.input_white as f32 / 255.0);
+        let range = (white - black).max(1.0 / 255.0);
+        let mut value = ((value - black) / range).clamp(0.0, 1.0);
+        if self.gamma > 0.0 {
+            value = value.powf(1.0 / self.gamma);
+        }
+
+        let (black, white) = (self.output_black as f32 / 255.0, self.output_white as f32 / 255.0);
+        black + value * (white - black)
+    }
+}
+
+/// A smooth tone curve through a set of points, like the Curves dialog of an image editor.
+/// The curve is a monotone cubic spline, so it never overshoots between the points.
+#[derive(Clone, PartialEq)]
+pub struct Curve {
+    points: Vec<(u8, u8)>, // The points the curve goes through, sorted by input
+    table: Vec<f32>,       // The curve sampled at 256 evenly spaced inputs
+}
+
+impl Curve {
+    /// Creates a curve through the given points, such as `[(0, 0), (64, 48), (192, 208), (255, 255)]` for an S-curve.
+    ///
+    /// # Arguments
+    ///
+    /// * `points` - The `(input, output)` points, from 0 to 255, in any order. Before the first and after the last point,
+    ///   the curve stays flat. With no points the curve changes nothing.
+    pub fn new(points: &[(u8, u8)]) -> Curve {
+        let mut points = points.to_vec();
+        points.sort_by_key(|point| point.0);
+        points.dedup_by_key(|point| point.0);
+
+        let knots: Vec<(f32, f32)> = points.iter().map(|&(x, y)| (x as f32, y as f32)).collect();
+        let table = match knots.len() {
+            0 => (0..256).map(|x| x as f32 / 255.0).collect(),
+            1 => vec![knots[0].1 / 255.0; 256],
+            _ => {
+                let tangents = monotone_tangents(&knots);
+                (0..256).map(|x| hermite(&knots, &tangents, x as f32) / 255.0).collect()
+            }
+        };
+        Curve { points, table }
+    }
+
+    /// Private function; maps one channel from 0 to 1, interpolating between the entries of the table.
+    fn apply(&self, value: f32) -> f32 {
+        let position = value.clamp(0.0, 1.0) * 255.0;
+        let index = (position as usize).min(254);
+        let fraction = position - index as f32;
+        self.table[index] + (self.table[index + 1] - self.table[index]) * fraction
+    }
+}
+
+impl fmt::Debug for Curve {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Curve").field("points", &self.points).finish()
+    }
+}
+
+impl Default for Curve {
+    /// A straight curve that changes nothing.
+    fn default() -> Self {
+        Curve::new(&[])
+    }
+}
+
+/// Private function; the tangents of a monotone cubic spline through sorted points, using the Fritsch-Carlson method.
+fn monotone_tangents(points: &[(f32, f32)]) -> Vec<f32> {
+    let slopes: Vec<f32> = points.windows(2).map(|pair| (pair[1].1 - pair[0].1) / (pair[1].0 - pair[0].0)).collect();
+    let mut tangents = Vec::with_capacity(points.len());
+    tangents.push(slopes[0]);
+    for pair in slopes.windows(2) {
+        // Flat at peaks and valleys so the curve doesn't wobble past them
+        tangents.push(if pair[0] * pair[1] <= 0.0 { 0.0 } else { (pair[0] + pair[1]) / 2.0 });
+    }
+    tangents.push(slopes[slopes.len() - 1]);
+
+    for (i, slope) in slopes.iter().enumerate() {
+        if *slope == 0.0 {
+            tangents[i] = 0.0;
+            tangents[i + 1] = 0.0;
+            continue;
+        }
+        let (a, b) = (tangents[i] / slope, tangents[i + 1] / slope);
+        let length = (a * a + b * b).sqrt();
+        if length > 3.0 {
+            tangents[i] = 3.0 / length * a * slope;
+            tangents[i + 1] = 3.0 / length * b * slope;
+        }
+    }
+    tangents
+}
+
+/// Private function; evaluates a cubic Hermite spline through sorted points at `x`, staying flat outside of them.
+fn hermite(points: &[(f32, f32)], tangents: &[f32], x: f32) -> f32 {
+    let last = points.len() - 1;
+    if x <= points[0].0 {
+        return points[0].1;
+    }
+    if x >= points[last].0 {
+        return points[last].1;
+    }
+
+    let i = points.windows(2).position(|pair| x < pair[1].0).unwrap_or(last - 1);
+    let ((x0, y0), (x1, y1)) = (points[i], points[i + 1]);
+    let h = x1 - x0;
+    let t = (x - x0) / h;
+    let (t2, t3) = (t * t, t * t * t);
+
+    (2.0 * t3 - 3.0 * t2 + 1.0) * y0
+        + (t3 - 2.0 * t2 + t) * h * tangents[i]
+        + (-2.0 * t3 + 3.0 * t2) * y1
+        + (t3 - t2) * h * tangents[i + 1]
+}
+
+/// A colour adjustment. Adjustments are applied in the order they are given, like the adjustment layers of an image editor.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Adjustment {
+    Brightness(f32),          // Adds to every channel, from -1 (black) to 1 (white)
+    Contrast(f32),            // Spreads colours away from or towards grey, from -1 (flat grey) up, where 0 changes nothing
+    Saturation(f32),          // Makes colours stronger or weaker, from -1 (greyscale) up, where 0 changes nothing
+    Vibrance(f32),            // Like saturation, but changes dull colours more than strong ones, which spares skin tones
+    Hue(f32),                 // Rotates every hue by this many degrees
+    Gamma(f32),               // Brightens the midtones above 1 and darkens them below 1
+    Exposure(f32),            // Brightens or darkens by this many stops, like a camera's exposure
+    Levels(Channel, Levels),  // Input and output levels on one or all channels
+    WhiteBalance {
+        temperature: f32,     // Makes the frame warmer above 0 and cooler below 0, from -1 to 1
+        tint: f32,            // Makes the frame more magenta above 0 and more green below 0, from -1 to 1
+    },
+    Curves(Channel, Curve),   // A tone curve on one or all channels
+    SplitTone {
+        shadows: Pixel,       // The colour the shadows are tinted towards; stronger colours tint more
+        highlights: Pixel,    // The colour the highlights are tinted towards; stronger colours tint more
+        balance: f32,         // Moves the split towards the shadows below 0 and towards the highlights above 0, from -1 to 1
+        amount: f32,          // How strongly the tints are added, from 0 (not at all) to 1 (their full colour)
+    },
+}
+
+impl Adjustment {
+    /// Applies the adjustment to a colour with channels from 0 to 1.
+    ///
+    /// # Arguments
+    ///
+    /// * `rgb` - The red, green and blue channels.
+    ///
+    /// # Returns
+    ///
+    /// The adjusted channels. They can leave the 0 to 1 range, and are clamped once all adjustments are done.
+    pub fn apply(&self, rgb: [f32; 3]) -> [f32; 3] {
+        match self {
+            Adjustment::Brightness(amount) => rgb.map(|value| value + amount),
+            Adjustment::Contrast(amount) => rgb.map(|value| (value - 0.5) * (1.0 + amount).max(0.0) + 0.5),
+            Adjustment::Saturation(amount) => saturate(rgb, (1.0 + amount).max(0.0)),
+            Adjustment::Vibrance(amount) => {
+                let max = rgb[0].max(rgb[1]).max(rgb[2]);
+                let min = rgb[0].min(rgb[1]).min(rgb[2]);
+                let saturation = (max - min).clamp(0.0, 1.0);
+                saturate(rgb, (1.0 + amount * (1.0 - saturation)).max(0.0))
+            }
+            Adjustment::Hue(degrees) => rotate_hue(rgb, *degrees),
+            Adjustment::Gamma(gamma) => {
+                if *gamma > 0.0 {
+                    rgb.map(|value| value.max(0.0).powf(1.0 / gamma))
+                } else {
+                    rgb
+                }
+            }
+            Adjustment::Exposure(stops) => {
+                let gain = 2f32.powf(*stops);
+                rgb.map(|value| linear_to_srgb(srgb_to_linear(value.max(0.0)) * gain))
+            }
+            Adjustment::Levels(channel, levels) => per_channel(rgb, *channel, |value| levels.apply(value)),
+            Adjustment::WhiteBalance { temperature, tint } => {
+                // Gains in linear light, scaled so white keeps its brightness
+                let gains = [1.0 + 0.3 * temperature, 1.0 - 0.3 * tint, 1.0 - 0.3 * temperature].map(|gain| gain.max(0.0));
+                let brightness: f32 = gains.iter().zip(LUMA).map(|(gain, weight)| gain * weight).sum();
+                let mut rgb = rgb;
+                for (value, gain) in rgb.iter_mut().zip(gains) {
+                    *value = linear_to_srgb(srgb_to_linear(value.max(0.0)) * gain / brightness.max(f32::EPSILON));
+                }
+                rgb
+            }
+            Adjustment::Curves(channel, curve) => per_channel(rgb, *channel, |value| curve.apply(value)),
+            Adjustment::SplitTone { shadows, highlights, balance, amount } => {
+                // Only the colour of the tints is added, not their brightness, so the luma stays about the same
+                let chroma = |pixel: &Pixel| {
+                    let tint = [pixel.r, pixel.g, pixel.b].map(|value| value as f32 / 255.0);
+                    let luma = luma(tint);
+                    tint.map(|value| (value - luma) * amount.clamp(0.0, 1.0))
+                };
+                let (shadows, highlights) = (chroma(shadows), chroma(highlights));
+                let pivot = 0.5 + balance.clamp(-1.0, 1.0) * 0.5;
+                let t = (luma(rgb) - pivot + 0.5).clamp(0.0, 1.0);
+                let highlight = t * t * (3.0 - 2.0 * t);
+                [0, 1, 2].map(|i| rgb[i] + shadows[i] * (1.0 - highlight) + highlights[i] * highlight)
+            }
+        }
+    }
+}
+
+/// Private function; applies `function` to the channels that `channel` includes.
+fn per_channel(rgb: [f32; 3], channel: Channel, function: impl Fn(f32) -> f32) -> [f32; 3] {
+    let mut rgb = rgb;
+    for (index, value) in rgb.iter_mut().enumerate() {
+        if channel.includes(index) {
+            *value = function(*value);
+        }
+    }
+    rgb
+}
+
+/// Private function; the Rec. 709 luma of a colour.
+pub(crate) fn luma(rgb: [f32; 3]) -> f32 {
+    rgb.iter().zip(LUMA).map(|(value, weight)| value * weight).sum()
+}
+
+/// Private function; scales how far each channel is from the luma of the colour.
+fn saturate(rgb: [f32; 3], factor: f32) -> [f32; 3] {
+    let luma = luma(rgb);
+    rgb.map(|value| luma + (value - luma) * factor)
+}
+
+/// Private function; rotates the hue of a colour around the grey axis, keeping its luma, like the CSS `hue-rotate()` filter.
+fn rotate_hue(rgb: [f32; 3], degrees: f32) -> [f32; 3] {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let matrix = [
+        [0.213 + cos * 0.787 - sin * 0.213, 0.715 - cos * 0.715 - sin * 0.715, 0.072 - cos * 0.072 + sin * 0.928],
+        [0.213 - cos * 0.213 + sin * 0.143, 0.715 + cos * 0.285 + sin * 0.140, 0.072 - cos * 0.072 - sin * 0.283],
+        [0.213 - cos * 0.213 - sin * 0.787, 0.715 - cos * 0.715 + sin * 0.715, 0.072 + cos * 0.928 + sin * 0.072],
+    ];
+    matrix.map(|row| row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2])
+}
+
+/// A type of colour blindness, simulated by `ColorMatrix::color_blindness`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorBlindness {
+    Protanopia,    // No red cones, so reds look dark and are confused with greens
+    Deuteranopia,  // No green cones, the most common kind, so reds and greens are confused
+    Tritanopia,    // No blue cones, so blues and greens, and yellows and reds, are confused
+    Achromatopsia, // No colour vision at all
+}
+
+/// A 4x5 matrix that works out every channel of a pixel as a weighted sum of its red, green, blue and alpha channels plus an offset,
+/// like the SVG `feColorMatrix` filter. Channels and offsets are from 0 to 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorMatrix {
+    pub rows: [[f32; 5]; 4], // The red, green, blue and alpha rows, each with the weights of r, g, b and a and then the offset
+}
+
+impl Default for ColorMatrix {
+    /// The identity matrix.
+    fn default() -> Self {
+        ColorMatrix::identity()
+    }
+}
+
+impl ColorMatrix {
+    /// Creates a matrix from its red, green, blue and alpha rows.
+    pub fn new(rows: [[f32; 5]; 4]) -> ColorMatrix {
+        ColorMatrix { rows }
+    }
+
+    /// Private function; creates a matrix that only mixes the colour channels, leaving alpha alone.
+    fn rgb(matrix: [[f32; 3]; 3], offset: [f32; 3]) -> ColorMatrix {
+        let row = |i: usize| [matrix[i][0], matrix[i][1], matrix[i][2], 0.0, offset[i]];
+        ColorMatrix::new([row(0), row(1), row(2), [0.0, 0.0, 0.0, 1.0, 0.0]])
+    }
+
+    /// Creates a matrix that changes nothing.
+    pub fn identity() -> ColorMatrix {
+        ColorMatrix::rgb([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]], [0.0; 3])
+    }
+
+    /// Creates a matrix that turns colours into greys of the same brightness.
+    pub fn greyscale(luma: Luma) -> ColorMatrix {
+        let weights = luma.weights();
+        ColorMatrix::rgb([weights; 3], [0.0; 3])
+    }
+
+    /// Creates the classic sepia tone of old photographs.
+    pub fn sepia() -> ColorMatrix {
+        ColorMatrix::rgb([
+            [0.393, 0.769, 0.189],
+            [0.349, 0.686, 0.168],
+            [0.272, 0.534, 0.131],
+        ], [0.0; 3])
+    }
+
+    /// Creates a duotone, which maps black to `shadows`, white to `highlights` and the greys in between to a blend of the two.
+    pub fn duotone(shadows: Pixel, highlights: Pixel, luma: Luma) -> ColorMatrix {
+        let weights = luma.weights();
+        let (low, high) = ([shadows.r, shadows.g, shadows.b], [highlights.r, highlights.g, highlights.b]);
+        let range = |i: usize| (high[i] as f32 - low[i] as f32) / 255.0;
+        ColorMatrix::rgb(
+            [0, 1, 2].map(|i| weights.map(|weight| weight * range(i))),
+            low.map(|value| value as f32 / 255.0),
+        )
+    }
+
+    /// Creates a matrix that fills each channel from another one, such as `channel_swap(Channel::Blue, Channel::Green, Channel::Red)`
+    /// to swap red and blue. `Channel::Rgb` fills the channel with the Rec. 709 luma.
+    pub fn channel_swap(red: Channel, green: Channel, blue: Channel) -> ColorMatrix {
+        let source = |channel: Channel| match channel {
+            Channel::Rgb => LUMA,
+            Channel::Red => [1.0, 0.0, 0.0],
+            Channel::Green => [0.0, 1.0, 0.0],
+            Channel::Blue => [0.0, 0.0, 1.0],
+        };
+        ColorMatrix::rgb([source(red), source(green), source(blue)], [0.0; 3])
+    }
+
+    /// Creates a matrix that inverts the colours, like a photographic negative. Alpha is left alone.
+    pub fn invert() -> ColorMatrix {
+        ColorMatrix::rgb([[-1.0, 0.0, 0.0], [0.0, -1.0, 0.0], [0.0, 0.0, -1.0]], [1.0; 3])
+    }
+
+    /// Creates a matrix that simulates how a colour blind viewer sees the frame, for checking that graphics stay readable.
+    /// The matrices are those of Machado, Oliveira and Fernandes (2009) at full severity.
+    pub fn color_blindness(kind: ColorBlindness) -> ColorMatrix {
+        let matrix = match kind {
+            ColorBlindness::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+            ColorBlindness::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            ColorBlindness::Tritanopia => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ],
+            ColorBlindness::Achromatopsia => return ColorMatrix::greyscale(Luma::Rec709),
+        };
+        ColorMatrix::rgb(matrix, [0.0; 3])
+    }
+
+    /// Returns a matrix that applies this one and then `next`.
+    pub fn then(&self, next: &ColorMatrix) -> ColorMatrix {
+        let mut rows = [[0.0; 5]; 4];
+        for (i, row) in rows.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| next.rows[i][k] * self.rows[k][j]).sum::<f32>();
+            }
+            row[4] += next.rows[i][4];
+        }
+        ColorMatrix { rows }
+    }
+
+    /// Applies the matrix to a colour with channels from 0 to 1.
+    ///
+    /// # Arguments
+    ///
+    /// * `rgba` - The red, green, blue and alpha channels.
+    ///
+    /// # Returns
+    ///
+    /// The new channels, clamped between 0 and 1.
+    pub fn apply(&self, rgba: [f32; 4]) -> [f32; 4] {
+        self.rows.map(|row| {
+            (row[0] * rgba[0] + row[1] * rgba[1] + row[2] * rgba[2] + row[3] * rgba[3] + row[4]).clamp(0.0, 1.0)
+        })
+    }
+}
+
+/// Private function; applies a colour matrix to every pixel.
+pub(crate) fn apply_matrix(pixels: &mut [Pixel], matrix: &ColorMatrix) {
+    pixels.par_iter_mut().for_each(|pixel| {
+        let rgba = [pixel.r, pixel.g, pixel.b, pixel.a].map(|value| value as f32 / 255.0);
+        let [r, g, b, a] = matrix.apply(rgba).map(|value| (value * 255.0).round() as u8);
+        *pixel = Pixel::rgba(r, g, b, a);
+    });
+}
+
+/// Private function; applies every adjustment, in order, to every pixel. Alpha is left as it is.
+pub(crate) fn adjust(pixels: &mut [Pixel], adjustments: &[Adjustment]) {
+    if adjustments.is_empty() {
+        return;
+    }
+
+    pixels.par_iter_mut().for_each(|pixel| {
+        let rgb = [pixel.r, pixel.g, pixel.b].map(|value| value as f32 / 255.0);
+        let [r, g, b] = adjustments
+            .iter()
+            .fold(rgb, |rgb, adjustment| adjustment.apply(rgb))
+            .map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as u8);
+        *pixel = Pixel::rgba(r, g, b, pixel.a);
+    });
+}
@@ -1,55 +1,204 @@
-use std::os::windows::process::CommandExt;
-use std::process::Command;
+use crate::vfx::encode::EncodeOptions;
+use crate::vfx::error::RsframeError;
+use crate::vfx::process::{command, run, spawn};
+use crate::vfx::progress::Monitor;
+use crate::vfx::source::FrameSource;
+use crate::vfx::tools::Tools;
+use crate::vfx::video::{Frame, FrameRate};
+use rayon::prelude::*;
+use std::fs;
+use std::io::{self, Write};
 use std::path::Path;
+use std::process::Stdio;
+use std::time::Instant;
+
+/// Builds a video out of a folder of `image1.bmp`, `image2.bmp`, ... frames, such as the one kept by `Video::save`.
+///
+/// # Arguments
+///
+/// * `folder_path` - The folder containing the frames.
+/// * `framerate` - The frame rate of the output video.
+/// * `location` - The path where the video will be saved.
+/// * `options` - The codec, quality and container to encode with.
+/// * `tools` - The tools to encode with, such as `Tools::global()`.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure of the render, or `UnsupportedEncoder` if ffmpeg lacks the codec.
+/// Lossless options with a codec that has no lossless mode return `UnsupportedLossless`.
+pub fn build_folder(folder_path: String, framerate: FrameRate, location: String, options: &EncodeOptions, tools: &Tools) -> Result<(), RsframeError> {
+    tools.require_encoder(options.encoder())?;
 
-pub fn build_folder(folder_path: String, framerate: i32, location: String, ffmpeg: &str) -> Result<(), ()> {
     // Ensure the input images exist
     let folder_path = Path::new(&folder_path);
 
     // Check for existing image files
-    let image_files: Vec<_> = std::fs::read_dir(folder_path)
-        .map_err(|_| ())?
+    let has_images = std::fs::read_dir(folder_path)?
         .filter_map(|entry| entry.ok())
-        .filter(|entry| {
+        .any(|entry| {
             if let Some(ext) = entry.path().extension() {
                 ext == "bmp"
             } else {
                 false
             }
-        })
-        .collect();
+        });
 
-    if image_files.is_empty() {
-        eprintln!("No BMP images found in the specified folder");
-        return Err(());
+    if !has_images {
+        return Err(RsframeError::Io(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no BMP images found in {}", folder_path.display()),
+        )));
     }
 
     // Construct the input pattern for FFmpeg (all BMP files in the folder)
     let input_pattern = folder_path.join("image%d.bmp").to_string_lossy().to_string();
 
     // Execute FFmpeg command to convert images to video
-    let output = Command::new(ffmpeg)
-        .creation_flags(0x08000000)
-        .args(&[
+    let started = Instant::now();
+    run(command(&tools.ffmpeg)
+        .args([
             "-framerate", &framerate.to_string(),
             "-i", &input_pattern,
-            "-vf", "scale=trunc(iw/2)*2:trunc(ih/2)*2", // Ensure even resolution
-            "-c:v", "libx264",  // Use H.264 video codec
-            "-preset", "medium",
-            "-crf", "23",        // Reasonable quality setting
-            "-pix_fmt", "yuv420p", // Ensure compatibility
+        ])
+        .args(options.to_args()?)
+        .args([
             "-y",  // Overwrite output file if it exists
             &location
+        ]))?;
+
+    log::info!(location = location.as_str(), elapsed:? = started.elapsed(); "built {} from {}", location, folder_path.display());
+    Ok(())
+}
+
+/// Saves any `FrameSource` to the specified location, streaming frames into FFmpeg while it encodes.
+/// With a `FileSource`, frames are decoded, edited and encoded one batch at a time, so the video never has to fit in memory.
+///
+/// # Arguments
+///
+/// * `source` - The frames to save.
+/// * `export_location` - The path where the video will be saved.
+/// * `options` - The codec, quality and container to encode with. The source's frame rate is used unless `options` sets one.
+///   Progress is reported into `options.monitor`, one step per frame.
+/// * `tools` - The tools to encode with, such as `Tools::global()`.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure of the render, or `UnsupportedEncoder` if ffmpeg lacks the codec.
+/// Lossless options with a codec that has no lossless mode return `UnsupportedLossless`.
+/// A source with no frames returns `NoFrames`, and a cancelled render returns `Cancelled`. A failed or cancelled render removes the unfinished output file.
+pub fn save_source<S: FrameSource + ?Sized>(source: &S, export_location: &str, options: &EncodeOptions, tools: &Tools) -> Result<(), RsframeError> {
+    tools.require_encoder(options.encoder())?;
+
+    let started = Instant::now();
+    // The length of a `FileSource` is only an estimate and may be unknown
+    let total = Some(source.len() as u64).filter(|&total| total > 0);
+    let frames = options.monitor.run("Saving", total, || {
+        encode_frames(source, export_location, options, &tools.ffmpeg)
+    });
+
+    match frames {
+        Ok(frames) => {
+            let elapsed = started.elapsed();
+            log::info!(location = export_location, frames = frames, elapsed:? = elapsed; "saved {} frames to {} in {:.2?}", frames, export_location, elapsed);
+            Ok(())
+        }
+        Err(RsframeError::Cancelled) => {
+            log::info!(location = export_location; "saving {} was cancelled", export_location);
+            Err(RsframeError::Cancelled)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Private function; encodes a source by writing raw RGB data into ffmpeg's stdin, so nothing is written to disk but the output.
+/// Returns the number of frames that were encoded. If encoding fails for any reason, the unfinished output is removed.
+fn encode_frames<S: FrameSource + ?Sized>(
+    source: &S,
+    location: &str,
+    options: &EncodeOptions,
+    ffmpeg: &Path,
+) -> Result<usize, RsframeError> {
+    let framerate = options.frame_rate.unwrap_or(source.frame_rate());
+
+    let mut tool = spawn(command(ffmpeg)
+        .args([
+            "-v", "error",
+            "-f", "rawvideo",
+            "-pix_fmt", "rgb24",
+            "-s", &format!("{}x{}", source.width(), source.height()),
+            "-framerate", &framerate.to_string(),
+            "-i", "-",
+        ])
+        .args(options.to_args()?)
+        .args([
+            "-y",  // Overwrite output file if it exists
+            location
         ])
-        .output()
-        .map_err(|_| ())?;  // Convert any execution error to ()
-
-    // Check if the command was successful
-    if output.status.success() {
-        Ok(())
-    } else {
-        // Log the full error output
-        eprintln!("FFmpeg error: {}", String::from_utf8_lossy(&output.stderr));
-        Err(())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null()))?;
+
+    let written = match tool.child.stdin.take() {
+        Some(mut stdin) => write_frames(&mut stdin, source, &options.monitor),
+        None => Ok(0),
+    };
+
+    let encoded = match written {
+        // If ffmpeg stopped early, its own error explains more than the broken pipe does
+        Err(WriteError::Pipe(err)) => tool.wait().and(Err(RsframeError::Io(err))),
+        Err(WriteError::Source(err)) => {
+            // Stop ffmpeg so it doesn't finish a file that only has some of the frames
+            tool.stop();
+            Err(err)
+        }
+        Ok(0) => {
+            tool.stop();
+            Err(RsframeError::NoFrames)
+        }
+        Ok(frames) => {
+            options.monitor.progress.message("Finishing");
+            tool.wait().map(|_| frames)
+        }
+    };
+
+    if encoded.is_err() {
+        let _ = fs::remove_file(location);
     }
-}
\ No newline at end of file
+    encoded
+}
+
+/// Private enum; why writing frames into ffmpeg stopped, since a broken pipe and a failing source are handled differently.
+enum WriteError {
+    Pipe(io::Error),      // Writing into ffmpeg's stdin failed, usually because ffmpeg exited
+    Source(RsframeError), // The source could not produce a frame, or the monitor cancelled the render
+}
+
+/// Private function; reads frames in batches, converts each batch to RGB in parallel and writes it in order.
+/// Returns the number of frames that were written.
+fn write_frames<S: FrameSource + ?Sized>(writer: &mut impl Write, source: &S, monitor: &Monitor) -> Result<usize, WriteError> {
+    let batch_size = rayon::current_num_threads().max(1) * 2;
+    let (width, height) = (source.width(), source.height());
+    let mut frames = source.frames();
+    let mut written = 0;
+
+    loop {
+        monitor.cancel.check().map_err(WriteError::Source)?;
+        let batch = frames.by_ref().take(batch_size).collect::<Result<Vec<Frame>, _>>().map_err(WriteError::Source)?;
+        if batch.is_empty() {
+            break;
+        }
+
+        if let Some(frame) = batch.iter().find(|frame| frame.width != width || frame.height != height) {
+            return Err(WriteError::Source(RsframeError::dimensions(width, height, frame.width, frame.height)));
+        }
+
+        let buffers: Vec<Vec<u8>> = batch.par_iter().map(|frame| frame.to_rgb()).collect();
+        for buffer in buffers {
+            writer.write_all(&buffer).map_err(WriteError::Pipe)?;
+            written += 1;
+            monitor.step().map_err(WriteError::Source)?;
+        }
+    }
+
+    writer.flush().map_err(WriteError::Pipe)?;
+    Ok(written)
+}
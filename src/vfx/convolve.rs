@@ -0,0 +1,396 @@
+use crate::vfx::error::RsframeError;
+use crate::vfx::video::Pixel;
+use rayon::prelude::*;
+
+/// How a convolution reads pixels past the edges of the frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EdgeMode {
+    #[default]
+    Clamp,  // Repeats the pixels on the edge
+    Wrap,   // Continues from the opposite edge, for frames that tile
+    Mirror, // Reflects the frame at the edge, without repeating the edge pixel
+}
+
+impl EdgeMode {
+    /// Private function; maps a coordinate that may lie outside of `0..length` onto a pixel inside it.
+    fn index(self, i: i64, length: usize) -> usize {
+        let length = length as i64;
+        if (0..length).contains(&i) {
+            return i as usize;
+        }
+
+        match self {
+            EdgeMode::Clamp => i.clamp(0, length - 1) as usize,
+            EdgeMode::Wrap => i.rem_euclid(length) as usize,
+            EdgeMode::Mirror => {
+                if length == 1 {
+                    return 0;
+                }
+                let period = 2 * (length - 1);
+                let i = i.rem_euclid(period);
+                (if i >= length { period - i } else { i }) as usize
+            }
+        }
+    }
+}
+
+/// A rectangle of pixels, such as the part of a frame that a filter is limited to.
+/// Whatever lies outside of the frame is ignored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub x: usize,      // The x-coordinate of the top left corner
+    pub y: usize,      // The y-coordinate of the top left corner
+    pub width: usize,  // The width of the rectangle
+    pub height: usize, // The height of the rectangle
+}
+
+impl Region {
+    /// Creates a rectangle from its top left corner and its size.
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Region {
+        Region { x, y, width, height }
+    }
+
+    /// Private function; the part of the rectangle that lies inside a `width` by `height` frame.
+    pub(crate) fn clip(self, width: usize, height: usize) -> Region {
+        let x = self.x.min(width);
+        let y = self.y.min(height);
+        Region {
+            x,
+            y,
+            width: self.width.min(width - x),
+            height: self.height.min(height - y),
+        }
+    }
+}
+
+/// A grid of weights that every pixel is replaced with the weighted sum of its neighbours by, centred on the pixel.
+/// Kernels that are one row times one column, such as blurs, are applied in two fast passes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Kernel {
+    width: usize,                              // The number of columns, which is odd
+    height: usize,                             // The number of rows, which is odd
+    values: Vec<f32>,                          // The weights, row by row
+    separable: Option<(Vec<f32>, Vec<f32>)>,   // The row and column the weights are the product of, if they are
+}
+
+impl Kernel {
+    /// Creates a kernel from its weights.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - The number of columns, which must be odd.
+    /// * `height` - The number of rows, which must be odd.
+    /// * `values` - The `width * height` weights, row by row. They are used as they are, so see `normalized` for blurs.
+    ///
+    /// # Returns
+    ///
+    /// The new `Kernel`, or an `InvalidKernel` error if the sizes are even or don't match the number of weights.
+    pub fn new(width: usize, height: usize, values: Vec<f32>) -> Result<Kernel, RsframeError> {
+        if width % 2 != 1 || height % 2 != 1 {
+            return Err(RsframeError::InvalidKernel(format!("{}x{} is not an odd size", width, height)));
+        }
+        if values.len() != width * height {
+            return Err(RsframeError::InvalidKernel(format!(
+                "a {}x{} kernel needs {} weights, found {}",
+                width, height, width * height, values.len()
+            )));
+        }
+
+        Ok(Kernel { width, height, values, separable: None })
+    }
+
+    /// Creates a kernel that is the product of a row and a column of weights, which is much faster to apply than a full grid.
+    ///
+    /// # Arguments
+    ///
+    /// * `horizontal` - The weights along a row, an odd number of them.
+    /// * `vertical` - The weights along a column, an odd number of them.
+    ///
+    /// # Returns
+    ///
+    /// The new `Kernel`, or an `InvalidKernel` error if either has an even number of weights.
+    pub fn separable(horizontal: Vec<f32>, vertical: Vec<f32>) -> Result<Kernel, RsframeError> {
+        let values = vertical.iter().flat_map(|y| horizontal.iter().map(move |x| x * y)).collect();
+        let mut kernel = Kernel::new(horizontal.len(), vertical.len(), values)?;
+        kernel.separable = Some((horizontal, vertical));
+        Ok(kernel)
+    }
+
+    /// Creates a Gaussian blur. The radius is the standard deviation in pixels, like the CSS `blur()` filter,
+    /// and the kernel reaches three times as far.
+    pub fn gaussian(radius: f32) -> Kernel {
+        let radius = radius.max(0.0);
+        let reach = (radius * 3.0).ceil() as i64;
+        let weights: Vec<f32> = (-reach..=reach)
+            .map(|i| if radius > 0.0 { (-(i * i) as f32 / (2.0 * radius * radius)).exp() } else { 1.0 })
+            .collect();
+        let weights = normalize(weights);
+        Kernel::separable(weights.clone(), weights).expect("Gaussian kernels have an odd size")
+    }
+
+    /// Creates a box blur, which averages the `2 * radius + 1` pixels wide square around every pixel.
+    pub fn box_blur(radius: usize) -> Kernel {
+        let weights = vec![1.0 / (2 * radius + 1) as f32; 2 * radius + 1];
+        Kernel::separable(weights.clone(), weights).expect("box kernels have an odd size")
+    }
+
+    /// Creates a 3x3 sharpening kernel. An `amount` of 1 is a standard sharpen, and 0 changes nothing.
+    pub fn sharpen(amount: f32) -> Kernel {
+        let a = amount;
+        Kernel::new(3, 3, vec![
+            0.0, -a, 0.0,
+            -a, 1.0 + 4.0 * a, -a,
+            0.0, -a, 0.0,
+        ]).expect("the sharpen kernel is 3x3")
+    }
+
+    /// Creates a 3x3 emboss kernel, which makes edges look raised, lit from the top left.
+    pub fn emboss() -> Kernel {
+        Kernel::new(3, 3, vec![
+            -2.0, -1.0, 0.0,
+            -1.0, 1.0, 1.0,
+            0.0, 1.0, 2.0,
+        ]).expect("the emboss kernel is 3x3")
+    }
+
+    /// Returns the kernel with its weights scaled to add up to one, so it doesn't change the brightness of the frame.
+    /// Kernels whose weights add up to zero, such as edge detectors, are returned as they are.
+    pub fn normalized(self) -> Kernel {
+        match self.separable {
+            Some((horizontal, vertical)) => Kernel::separable(normalize(horizontal), normalize(vertical)).expect("the sizes are unchanged"),
+            None => Kernel { values: normalize(self.values), ..self },
+        }
+    }
+
+    /// Returns the number of columns.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the number of rows.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+}
+
+/// Private function; scales weights to add up to one, unless they add up to zero.
+fn normalize(weights: Vec<f32>) -> Vec<f32> {
+    let total: f32 = weights.iter().sum();
+    if total.abs() < f32::EPSILON {
+        weights
+    } else {
+        weights.into_iter().map(|weight| weight / total).collect()
+    }
+}
+
+/// Private function; converts pixels to floats with their colour premultiplied by their alpha, so transparent pixels don't bleed colour.
+pub(crate) fn premultiply(pixels: &[Pixel]) -> Vec<[f32; 4]> {
+    pixels
+        .par_iter()
+        .map(|pixel| {
+            let alpha = pixel.a as f32 / 255.0;
+            [pixel.r as f32 * alpha, pixel.g as f32 * alpha, pixel.b as f32 * alpha, alpha]
+        })
+        .collect()
+}
+
+/// Private function; turns premultiplied floats back into a pixel.
+pub(crate) fn unpremultiply(value: [f32; 4]) -> Pixel {
+    let alpha = value[3].clamp(0.0, 1.0);
+    if alpha <= 0.0 {
+        return Pixel::transparent();
+    }
+    let channel = |channel: f32| (channel / alpha).round().clamp(0.0, 255.0) as u8;
+    Pixel::rgba(channel(value[0]), channel(value[1]), channel(value[2]), (alpha * 255.0).round() as u8)
+}
+
+/// Private function; convolves the part of `pixels`, a `width` by `height` image stored row by row, inside `region`.
+/// Pixels outside of the region are read but left as they are.
+pub(crate) fn convolve(pixels: &mut [Pixel], width: usize, height: usize, kernel: &Kernel, edge: EdgeMode, region: Region) {
+    let region = region.clip(width, height);
+    if region.width == 0 || region.height == 0 {
+        return;
+    }
+
+    let source = premultiply(pixels);
+    let filtered = convolve_values(&source, width, height, kernel, edge, region);
+    write_region(pixels, width, region, &filtered, unpremultiply);
+}
+
+/// Private function; convolves premultiplied values and returns the result for the pixels inside `region`, row by row.
+pub(crate) fn convolve_values(
+    source: &[[f32; 4]],
+    width: usize,
+    height: usize,
+    kernel: &Kernel,
+    edge: EdgeMode,
+    region: Region,
+) -> Vec<[f32; 4]> {
+    let (reach_x, reach_y) = ((kernel.width / 2) as i64, (kernel.height / 2) as i64);
+    let mut filtered = vec![[0.0; 4]; region.width * region.height];
+
+    match &kernel.separable {
+        Some((horizontal, vertical)) => {
+            // Blur along the rows of only the lines the columns will read, from `reach_y` above the region to `reach_y` below it,
+            // then along the columns of the region
+            let top = region.y as i64 - reach_y;
+            let mut rows = vec![[0.0; 4]; region.width * (region.height + 2 * reach_y as usize)];
+            rows.par_chunks_mut(region.width).enumerate().for_each(|(r, row)| {
+                let y = edge.index(top + r as i64, height);
+                let line = &source[y * width..(y + 1) * width];
+                for (i, out) in row.iter_mut().enumerate() {
+                    let x = (region.x + i) as i64;
+                    for (k, weight) in horizontal.iter().enumerate() {
+                        let value = line[edge.index(x + k as i64 - reach_x, width)];
+                        add(out, value, *weight);
+                    }
+                }
+            });
+
+            filtered.par_chunks_mut(region.width).enumerate().for_each(|(j, row)| {
+                for (i, out) in row.iter_mut().enumerate() {
+                    for (k, weight) in vertical.iter().enumerate() {
+                        // Line `region.y + j + k - reach_y` of the image is line `j + k` of the buffer
+                        let value = rows[(j + k) * region.width + i];
+                        add(out, value, *weight);
+                    }
+                }
+            });
+        }
+        None => {
+            filtered.par_chunks_mut(region.width).enumerate().for_each(|(j, row)| {
+                let y = (region.y + j) as i64;
+                for (i, out) in row.iter_mut().enumerate() {
+                    let x = (region.x + i) as i64;
+                    for ky in 0..kernel.height {
+                        let line = edge.index(y + ky as i64 - reach_y, height) * width;
+                        for kx in 0..kernel.width {
+                            let weight = kernel.values[ky * kernel.width + kx];
+                            if weight != 0.0 {
+                                add(out, source[line + edge.index(x + kx as i64 - reach_x, width)], weight);
+                            }
+                        }
+                    }
+                }
+            });
+        }
+    }
+    filtered
+}
+
+/// Private function; adds a weighted value to a running sum.
+fn add(sum: &mut [f32; 4], value: [f32; 4], weight: f32) {
+    for channel in 0..4 {
+        sum[channel] += value[channel] * weight;
+    }
+}
+
+/// Private function; writes values for the pixels inside `region`, stored row by row, back into the image.
+pub(crate) fn write_region<T: Copy + Sync>(pixels: &mut [Pixel], width: usize, region: Region, values: &[T], convert: impl Fn(T) -> Pixel + Sync) {
+    pixels
+        .par_chunks_mut(width)
+        .skip(region.y)
+        .take(region.height)
+        .zip(values.par_chunks(region.width))
+        .for_each(|(row, values)| {
+            for (pixel, value) in row[region.x..region.x + region.width].iter_mut().zip(values) {
+                *pixel = convert(*value);
+            }
+        });
+}
+
+/// Private function; sharpens the part of `pixels` inside `region` with an unsharp mask: the difference between the image
+/// and a Gaussian blur of it is added back `amount` times, wherever it is more than `threshold`.
+pub(crate) fn unsharp_mask(pixels: &mut [Pixel], width: usize, height: usize, radius: f32, amount: f32, threshold: u8, region: Region) {
+    let region = region.clip(width, height);
+    if region.width == 0 || region.height == 0 {
+        return;
+    }
+
+    let source = premultiply(pixels);
+    let blurred = convolve_values(&source, width, height, &Kernel::gaussian(radius), EdgeMode::Clamp, region);
+    let sharpened: Vec<[f32; 4]> = blurred
+        .par_iter()
+        .enumerate()
+        .map(|(i, blurred)| {
+            let original = source[(region.y + i / region.width) * width + region.x + i % region.width];
+            let difference = [0, 1, 2].map(|channel| original[channel] - blurred[channel]);
+            if difference.iter().all(|difference| difference.abs() <= threshold as f32) {
+                return original;
+            }
+            let mut value = original;
+            for channel in 0..3 {
+                value[channel] += difference[channel] * amount;
+            }
+            value
+        })
+        .collect();
+
+    write_region(pixels, width, region, &sharpened, unpremultiply);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: usize = 19;
+    const HEIGHT: usize = 13;
+
+    /// A frame with a different colour and alpha in every pixel.
+    fn pattern() -> Vec<Pixel> {
+        (0..WIDTH * HEIGHT)
+            .map(|i| {
+                let (x, y) = (i % WIDTH, i / WIDTH);
+                Pixel::rgba((x * 13) as u8, (y * 19) as u8, ((x * y * 7) % 256) as u8, 128 + (x + y) as u8 * 4)
+            })
+            .collect()
+    }
+
+    /// Checks that blurring `region` gives the same pixels inside it as blurring the whole frame, and leaves the rest alone.
+    fn check_region(region: Region, edge: EdgeMode) {
+        let kernel = Kernel::gaussian(1.5);
+        let original = pattern();
+
+        let mut full = original.clone();
+        convolve(&mut full, WIDTH, HEIGHT, &kernel, edge, Region::new(0, 0, WIDTH, HEIGHT));
+        let mut partial = original.clone();
+        convolve(&mut partial, WIDTH, HEIGHT, &kernel, edge, region);
+
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                let i = y * WIDTH + x;
+                let inside = (region.x..region.x + region.width).contains(&x) && (region.y..region.y + region.height).contains(&y);
+                let expected = if inside { full[i] } else { original[i] };
+                assert_eq!(partial[i], expected, "pixel ({}, {}) with {:?}", x, y, edge);
+            }
+        }
+    }
+
+    #[test]
+    fn region_at_the_top_matches_a_full_blur() {
+        for edge in [EdgeMode::Clamp, EdgeMode::Wrap, EdgeMode::Mirror] {
+            check_region(Region::new(3, 0, 6, 2), edge);
+        }
+    }
+
+    #[test]
+    fn region_at_the_bottom_matches_a_full_blur() {
+        for edge in [EdgeMode::Clamp, EdgeMode::Wrap, EdgeMode::Mirror] {
+            check_region(Region::new(10, HEIGHT - 3, 9, 3), edge);
+        }
+    }
+
+    #[test]
+    fn region_in_the_middle_matches_a_full_blur() {
+        for edge in [EdgeMode::Clamp, EdgeMode::Wrap, EdgeMode::Mirror] {
+            check_region(Region::new(5, 5, 4, 3), edge);
+        }
+    }
+
+    #[test]
+    fn kernels_must_have_odd_sizes() {
+        assert!(matches!(Kernel::new(2, 3, vec![0.0; 6]), Err(RsframeError::InvalidKernel(_))));
+        assert!(matches!(Kernel::new(3, 3, vec![0.0; 8]), Err(RsframeError::InvalidKernel(_))));
+        assert!(Kernel::new(3, 1, vec![1.0, 2.0, 1.0]).is_ok());
+    }
+}
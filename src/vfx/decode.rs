@@ -0,0 +1,158 @@
+use crate::vfx::error::RsframeError;
+use crate::vfx::process::{command, spawn, Running};
+use crate::vfx::load::LoadOptions;
+use crate::vfx::video::{Frame, FrameRate};
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::{ChildStdout, Stdio};
+use std::time::Instant;
+
+/// Reads frames one at a time from ffmpeg's stdout as raw RGB data, without touching the disk.
+/// Only one frame is held in memory at a time; ffmpeg is stopped when the reader is dropped.
+pub struct FrameReader {
+    tool: Option<Running>,       // The ffmpeg process, until it has been waited for
+    stdout: Option<ChildStdout>, // The pipe the frames are read from
+    width: usize,                // The width of the decoded frames
+    height: usize,               // The height of the decoded frames
+    buffer: Vec<u8>,             // Room for exactly one frame of RGB data
+}
+
+impl FrameReader {
+    /// Starts decoding the frames of a file picked by `options`.
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - The path to the video file.
+    /// * `width` - The width of the decoded frames.
+    /// * `height` - The height of the decoded frames.
+    /// * `options` - The range, stride and frame limit to decode.
+    /// * `frame_rate` - The frame rate of the file, used to turn frame indices into timestamps.
+    /// * `ffmpeg` - The ffmpeg executable.
+    ///
+    /// # Returns
+    ///
+    /// A `FrameReader` that yields the frames in order.
+    pub fn open(
+        filename: &str,
+        width: usize,
+        height: usize,
+        options: &LoadOptions,
+        frame_rate: FrameRate,
+        ffmpeg: &Path,
+    ) -> Result<FrameReader, RsframeError> {
+        let mut tool = spawn(command(ffmpeg)
+            .args(["-v", "error"])
+            .args(options.input_args(frame_rate))
+            .arg("-i")
+            .arg(filename)
+            .args(options.output_args(frame_rate))
+            .args(["-f", "rawvideo", "-pix_fmt", "rgb24", "-"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped()))?;
+
+        let stdout = tool.child.stdout.take();
+        Ok(FrameReader {
+            tool: Some(tool),
+            stdout,
+            width,
+            height,
+            buffer: vec![0u8; width * height * 3],
+        })
+    }
+
+    /// Private function; closes the pipe and waits for ffmpeg, returning its error if it failed.
+    fn finish(&mut self) -> Result<(), RsframeError> {
+        drop(self.stdout.take());
+        match self.tool.take() {
+            Some(tool) => tool.wait(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Iterator for FrameReader {
+    type Item = Result<Frame, RsframeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let stdout = self.stdout.as_mut()?;
+
+        match read_frame(stdout, &mut self.buffer) {
+            Ok(true) => Some(Ok(Frame::from_rgb(self.width, self.height, &self.buffer))),
+            Ok(false) => self.finish().err().map(Err),
+            Err(err) => {
+                // If ffmpeg failed, its own error explains more than the broken pipe does
+                let finished = self.finish();
+                Some(Err(finished.err().unwrap_or(RsframeError::Io(err))))
+            }
+        }
+    }
+}
+
+impl Drop for FrameReader {
+    fn drop(&mut self) {
+        drop(self.stdout.take());
+        if let Some(tool) = self.tool.take() {
+            tool.stop();
+        }
+    }
+}
+
+/// Decodes the frames of a file picked by `options` into memory, reporting each frame into `options.monitor`.
+///
+/// # Arguments
+///
+/// * `filename` - The path to the video file.
+/// * `width` - The width of the decoded frames.
+/// * `height` - The height of the decoded frames.
+/// * `capacity` - The number of frames expected, so the frame buffer is only allocated once.
+/// * `options` - The range, stride and frame limit to decode.
+/// * `frame_rate` - The frame rate of the file, used to turn frame indices into timestamps.
+/// * `ffmpeg` - The ffmpeg executable.
+///
+/// # Returns
+///
+/// The decoded frames, in order, `NoFrames` if there were none, or `Cancelled` if the monitor was cancelled.
+pub fn decode_frames(
+    filename: &str,
+    width: usize,
+    height: usize,
+    capacity: usize,
+    options: &LoadOptions,
+    frame_rate: FrameRate,
+    ffmpeg: &Path,
+) -> Result<Vec<Frame>, RsframeError> {
+    let total = if capacity > 0 { Some(capacity as u64) } else { None };
+    let started = Instant::now();
+
+    let frames = options.monitor.run("Loading", total, || {
+        let mut frames = Vec::with_capacity(capacity);
+        for frame in FrameReader::open(filename, width, height, options, frame_rate, ffmpeg)? {
+            frames.push(frame?);
+            options.monitor.step()?;
+        }
+
+        if frames.is_empty() {
+            return Err(RsframeError::NoFrames);
+        }
+        Ok(frames)
+    })?;
+
+    let elapsed = started.elapsed();
+    log::info!(file = filename, frames = frames.len(), elapsed:? = elapsed; "decoded {} frames from {} in {:.2?}", frames.len(), filename, elapsed);
+    Ok(frames)
+}
+
+/// Private function; fills `buffer` with the next frame from `reader`.
+/// Returns `false` once the stream has ended, dropping any incomplete trailing frame.
+fn read_frame(reader: &mut impl Read, buffer: &mut [u8]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => return Ok(false),
+            Ok(n) => filled += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(true)
+}
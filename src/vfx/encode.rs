@@ -0,0 +1,307 @@
+use crate::vfx::error::RsframeError;
+use crate::vfx::progress::Monitor;
+use crate::vfx::video::FrameRate;
+
+/// A video codec that FFmpeg can encode to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Codec {
+    H264,          // H.264 / AVC through libx264
+    H265,          // H.265 / HEVC through libx265
+    Vp9,           // VP9 through libvpx-vp9
+    ProRes,        // Apple ProRes through prores_ks
+    Ffv1,          // FFV1, a lossless intermediate codec
+    Other(String), // Any other FFmpeg encoder, by name
+}
+
+impl Codec {
+    /// Returns the name of the FFmpeg encoder for the codec.
+    pub fn encoder(&self) -> &str {
+        match self {
+            Codec::H264 => "libx264",
+            Codec::H265 => "libx265",
+            Codec::Vp9 => "libvpx-vp9",
+            Codec::ProRes => "prores_ks",
+            Codec::Ffv1 => "ffv1",
+            Codec::Other(name) => name.as_str(),
+        }
+    }
+}
+
+/// How the encoder decides on the quality of the output.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Quality {
+    Crf(u8),      // Constant quality; lower is better. ProRes uses it as `-qscale:v`
+    Bitrate(u32), // A target bitrate in kilobits per second
+    Lossless,     // Lossless output; see `EncodeOptions::lossless` for which codecs support it
+}
+
+/// Everything `Video::save` and `build_folder` pass to FFmpeg about the output file.
+/// The presets cover the common cases, and every setting can be changed afterwards with the builder methods,
+/// for example `EncodeOptions::h265().crf(26).preset("slow")`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EncodeOptions {
+    pub codec: Codec,                    // The codec to encode with
+    pub quality: Quality,                // The quality mode of the encoder
+    pub preset: Option<String>,          // The encoder speed preset, such as `medium`
+    pub pixel_format: String,            // The pixel format of the output, such as `yuv420p`
+    pub container: Option<String>,       // The container format; guessed from the file extension if not set
+    pub extra_args: Vec<String>,         // Extra arguments passed to FFmpeg right before the output path
+    pub frame_rate: Option<FrameRate>,   // Overrides the frame rate of the video being saved
+    pub monitor: Monitor,                // Receives the progress of the render and can cancel it
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        EncodeOptions::h264()
+    }
+}
+
+impl EncodeOptions {
+    /// H.264 at CRF 23 with the `medium` preset in `yuv420p`; plays almost everywhere. This is the default.
+    pub fn h264() -> EncodeOptions {
+        EncodeOptions {
+            codec: Codec::H264,
+            quality: Quality::Crf(23),
+            preset: Some("medium".to_string()),
+            pixel_format: "yuv420p".to_string(),
+            container: None,
+            extra_args: Vec::new(),
+            frame_rate: None,
+            monitor: Monitor::default(),
+        }
+    }
+
+    /// H.265 at CRF 28 with the `medium` preset, tagged `hvc1` so Apple players accept it.
+    pub fn h265() -> EncodeOptions {
+        EncodeOptions {
+            codec: Codec::H265,
+            quality: Quality::Crf(28),
+            extra_args: vec!["-tag:v".to_string(), "hvc1".to_string()],
+            ..EncodeOptions::h264()
+        }
+    }
+
+    /// VP9 at CRF 31 in constant quality mode, for `.webm` files.
+    pub fn vp9() -> EncodeOptions {
+        EncodeOptions {
+            codec: Codec::Vp9,
+            quality: Quality::Crf(31),
+            preset: None,
+            ..EncodeOptions::h264()
+        }
+    }
+
+    /// ProRes 422 HQ in 10-bit `yuv422p10le`, for editing and delivery in `.mov` files.
+    pub fn prores() -> EncodeOptions {
+        EncodeOptions {
+            codec: Codec::ProRes,
+            quality: Quality::Crf(9),
+            preset: None,
+            pixel_format: "yuv422p10le".to_string(),
+            container: None,
+            extra_args: vec!["-profile:v".to_string(), "3".to_string()],
+            frame_rate: None,
+            monitor: Monitor::default(),
+        }
+    }
+
+    /// Lossless FFV1 in `rgb24`, so the frames come back exactly as they were saved. Use with `.mkv` files.
+    pub fn ffv1() -> EncodeOptions {
+        EncodeOptions {
+            codec: Codec::Ffv1,
+            quality: Quality::Lossless,
+            preset: None,
+            pixel_format: "rgb24".to_string(),
+            container: None,
+            extra_args: Vec::new(),
+            frame_rate: None,
+            monitor: Monitor::default(),
+        }
+    }
+
+    /// Sets the codec.
+    pub fn codec(mut self, codec: Codec) -> EncodeOptions {
+        self.codec = codec;
+        self
+    }
+
+    /// Uses constant quality mode with the given CRF.
+    pub fn crf(mut self, crf: u8) -> EncodeOptions {
+        self.quality = Quality::Crf(crf);
+        self
+    }
+
+    /// Uses a target bitrate, in kilobits per second.
+    pub fn bitrate(mut self, kbps: u32) -> EncodeOptions {
+        self.quality = Quality::Bitrate(kbps);
+        self
+    }
+
+    /// Makes the output lossless. H.264 switches to `libx264rgb` in `rgb24`, so the frames come back exactly as they were saved.
+    /// H.265 and VP9 switch chroma subsampled formats such as `yuv420p` to `yuv444p`, which keeps all of the colour detail,
+    /// although converting to YUV still rounds the colours slightly. ProRes has no lossless mode, so saving with it fails.
+    pub fn lossless(mut self) -> EncodeOptions {
+        self.quality = Quality::Lossless;
+        self
+    }
+
+    /// Sets the encoder speed preset, such as `ultrafast`, `medium` or `veryslow`.
+    pub fn preset(mut self, preset: &str) -> EncodeOptions {
+        self.preset = Some(preset.to_string());
+        self
+    }
+
+    /// Sets the pixel format of the output, such as `yuv420p` or `yuv444p`.
+    pub fn pixel_format(mut self, pixel_format: &str) -> EncodeOptions {
+        self.pixel_format = pixel_format.to_string();
+        self
+    }
+
+    /// Sets the container format, such as `mp4`, `matroska` or `mov`, instead of guessing it from the file extension.
+    pub fn container(mut self, container: &str) -> EncodeOptions {
+        self.container = Some(container.to_string());
+        self
+    }
+
+    /// Saves at the given frame rate instead of the frame rate of the video.
+    pub fn frame_rate(mut self, frame_rate: FrameRate) -> EncodeOptions {
+        self.frame_rate = Some(frame_rate);
+        self
+    }
+
+    /// Reports the progress of the render into `monitor`, which can also cancel it.
+    pub fn monitor(mut self, monitor: Monitor) -> EncodeOptions {
+        self.monitor = monitor;
+        self
+    }
+
+    /// Adds a free-form argument that is passed to FFmpeg right before the output path.
+    pub fn arg(mut self, arg: &str) -> EncodeOptions {
+        self.extra_args.push(arg.to_string());
+        self
+    }
+
+    /// Adds several free-form arguments that are passed to FFmpeg right before the output path.
+    pub fn args(mut self, args: &[&str]) -> EncodeOptions {
+        self.extra_args.extend(args.iter().map(|arg| arg.to_string()));
+        self
+    }
+
+    /// Returns the name of the FFmpeg encoder these options encode with. This is the codec's encoder,
+    /// apart from lossless H.264 in a chroma subsampled format, which is encoded in RGB with `libx264rgb`.
+    pub fn encoder(&self) -> &str {
+        if self.quality == Quality::Lossless && self.codec == Codec::H264 && is_subsampled(&self.pixel_format) {
+            "libx264rgb"
+        } else {
+            self.codec.encoder()
+        }
+    }
+
+    /// Private function; the pixel format that is actually encoded, since lossless output can't be chroma subsampled.
+    fn output_pixel_format(&self) -> String {
+        if self.quality != Quality::Lossless || !is_subsampled(&self.pixel_format) {
+            return self.pixel_format.clone();
+        }
+        match self.codec {
+            Codec::H264 => "rgb24".to_string(),
+            Codec::H265 | Codec::Vp9 => self.pixel_format.replace("420", "444").replace("422", "444"),
+            _ => self.pixel_format.clone(),
+        }
+    }
+
+    /// Returns the FFmpeg output arguments for these options, everything between the input and the output path.
+    ///
+    /// # Returns
+    ///
+    /// The arguments, or an `UnsupportedLossless` error if the output is lossless but the codec has no lossless mode.
+    pub fn to_args(&self) -> Result<Vec<String>, RsframeError> {
+        let mut args: Vec<String> = Vec::new();
+        let pixel_format = self.output_pixel_format();
+
+        // Chroma subsampled formats need even dimensions
+        if is_subsampled(&pixel_format) {
+            args.extend(["-vf".to_string(), "scale=trunc(iw/2)*2:trunc(ih/2)*2".to_string()]);
+        }
+
+        args.extend(["-c:v".to_string(), self.encoder().to_string()]);
+
+        if let Some(preset) = &self.preset {
+            args.extend(["-preset".to_string(), preset.clone()]);
+        }
+
+        match (&self.quality, &self.codec) {
+            (Quality::Crf(crf), Codec::ProRes) => args.extend(["-qscale:v".to_string(), crf.to_string()]),
+            (Quality::Crf(crf), Codec::Vp9) => args.extend(["-crf".to_string(), crf.to_string(), "-b:v".to_string(), "0".to_string()]),
+            (Quality::Crf(_), Codec::Ffv1) => {}
+            (Quality::Crf(crf), _) => args.extend(["-crf".to_string(), crf.to_string()]),
+            (Quality::Bitrate(kbps), _) => args.extend(["-b:v".to_string(), format!("{}k", kbps)]),
+            (Quality::Lossless, Codec::H264) => args.extend(["-qp".to_string(), "0".to_string()]),
+            (Quality::Lossless, Codec::H265) => args.extend(["-x265-params".to_string(), "lossless=1".to_string()]),
+            (Quality::Lossless, Codec::Vp9) => args.extend(["-lossless".to_string(), "1".to_string()]),
+            (Quality::Lossless, Codec::Ffv1) => {}
+            (Quality::Lossless, Codec::ProRes) => return Err(RsframeError::UnsupportedLossless(self.encoder().to_string())),
+            (Quality::Lossless, Codec::Other(name)) => {
+                // There is no way of knowing how other encoders turn on lossless mode, so it has to be passed with `arg`
+                log::warn!(encoder = name.as_str(); "lossless output with `{}` needs the encoder's own arguments", name);
+            }
+        }
+
+        args.extend(["-pix_fmt".to_string(), pixel_format]);
+        args.extend(self.extra_args.iter().cloned());
+
+        if let Some(container) = &self.container {
+            args.extend(["-f".to_string(), container.clone()]);
+        }
+
+        Ok(args)
+    }
+}
+
+/// Private function; whether a pixel format stores colour at a lower resolution than brightness, such as `yuv420p`.
+fn is_subsampled(pixel_format: &str) -> bool {
+    pixel_format.contains("420") || pixel_format.contains("422")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lossless_h264_encodes_in_rgb() {
+        let options = EncodeOptions::h264().lossless();
+        let args = options.to_args().unwrap();
+
+        assert_eq!(options.encoder(), "libx264rgb");
+        assert!(args.windows(2).any(|pair| pair == ["-pix_fmt", "rgb24"]));
+        assert!(args.windows(2).any(|pair| pair == ["-qp", "0"]));
+        assert!(!args.iter().any(|arg| arg.contains("yuv420p") || arg.contains("scale")));
+    }
+
+    #[test]
+    fn lossless_vp9_is_not_subsampled() {
+        let args = EncodeOptions::vp9().lossless().to_args().unwrap();
+
+        assert!(args.windows(2).any(|pair| pair == ["-pix_fmt", "yuv444p"]));
+        assert!(args.windows(2).any(|pair| pair == ["-lossless", "1"]));
+        assert!(!args.iter().any(|arg| arg.contains("yuv420p") || arg.contains("scale")));
+    }
+
+    #[test]
+    fn lossless_keeps_an_explicit_full_resolution_format() {
+        let options = EncodeOptions::h264().pixel_format("yuv444p").lossless();
+        assert_eq!(options.encoder(), "libx264");
+        assert!(options.to_args().unwrap().windows(2).any(|pair| pair == ["-pix_fmt", "yuv444p"]));
+    }
+
+    #[test]
+    fn lossless_prores_is_rejected() {
+        assert!(matches!(EncodeOptions::prores().lossless().to_args(), Err(RsframeError::UnsupportedLossless(_))));
+    }
+
+    #[test]
+    fn subsampled_output_is_scaled_to_even_dimensions() {
+        let args = EncodeOptions::h264().crf(18).to_args().unwrap();
+        assert_eq!(args[..2], ["-vf", "scale=trunc(iw/2)*2:trunc(ih/2)*2"]);
+        assert!(args.windows(2).any(|pair| pair == ["-crf", "18"]));
+    }
+}
@@ -0,0 +1,111 @@
+use std::fmt;
+use std::io;
+
+/// Every error that `rsframe` can return.
+#[derive(Debug)]
+pub enum RsframeError {
+    /// An external tool (ffmpeg, ffprobe) could not be started because it was not found.
+    ToolNotFound {
+        tool: String, // The program that was looked up
+    },
+    /// An external tool ran but exited unsuccessfully.
+    ToolFailed {
+        tool: String,   // The program that failed
+        stderr: String, // Everything the program wrote to stderr
+    },
+    /// Reading or writing a file failed.
+    Io(io::Error),
+    /// An image could not be decoded or encoded.
+    ImageDecode(image::ImageError),
+    /// A frame or video does not have the size that was expected.
+    DimensionMismatch {
+        expected: (usize, usize), // The expected width and height
+        found: (usize, usize),    // The width and height that were given
+    },
+    /// A range of frames or pixels lies outside of what is available.
+    InvalidRange {
+        start: usize,  // The start of the requested range
+        end: usize,    // The end of the requested range
+        length: usize, // The length of the data the range was applied to
+    },
+    /// An operation produced or received no frames at all.
+    NoFrames,
+    /// A file could not be understood as media, or does not have what was asked of it.
+    InvalidMedia(String),
+    /// The installed ffmpeg was not built with the encoder that was asked for.
+    UnsupportedEncoder(String),
+    /// Lossless output was asked for, but the encoder has no lossless mode.
+    UnsupportedLossless(String),
+    /// The operation was stopped by its `CancelToken`.
+    Cancelled,
+    /// A font file could not be read as a TrueType or OpenType font.
+    InvalidFont(String),
+    /// A convolution kernel does not have an odd width and height, or the wrong number of weights.
+    InvalidKernel(String),
+    /// A LUT file could not be read, such as a `.cube` file with a missing size or the wrong number of entries.
+    InvalidLut(String),
+}
+
+impl RsframeError {
+    /// Creates a `DimensionMismatch` error from the expected and the given sizes.
+    pub(crate) fn dimensions(expected_width: usize, expected_height: usize, width: usize, height: usize) -> RsframeError {
+        RsframeError::DimensionMismatch {
+            expected: (expected_width, expected_height),
+            found: (width, height),
+        }
+    }
+}
+
+impl fmt::Display for RsframeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RsframeError::ToolNotFound { tool } => write!(f, "could not find `{}`, is it installed and added to PATH?", tool),
+            RsframeError::ToolFailed { tool, stderr } => write!(f, "`{}` did not exit successfully: {}", tool, stderr.trim()),
+            RsframeError::Io(err) => write!(f, "I/O error: {}", err),
+            RsframeError::ImageDecode(err) => write!(f, "image error: {}", err),
+            RsframeError::DimensionMismatch { expected, found } => write!(
+                f,
+                "dimension mismatch: expected {}x{}, found {}x{}",
+                expected.0, expected.1, found.0, found.1
+            ),
+            RsframeError::InvalidRange { start, end, length } => write!(
+                f,
+                "invalid range: start={} end={} length={}",
+                start, end, length
+            ),
+            RsframeError::NoFrames => write!(f, "no frames were found"),
+            RsframeError::InvalidMedia(reason) => write!(f, "invalid media: {}", reason),
+            RsframeError::UnsupportedEncoder(encoder) => write!(f, "ffmpeg was not built with the `{}` encoder", encoder),
+            RsframeError::UnsupportedLossless(encoder) => write!(f, "the `{}` encoder has no lossless mode", encoder),
+            RsframeError::Cancelled => write!(f, "the operation was cancelled"),
+            RsframeError::InvalidFont(reason) => write!(f, "invalid font: {}", reason),
+            RsframeError::InvalidKernel(reason) => write!(f, "invalid kernel: {}", reason),
+            RsframeError::InvalidLut(reason) => write!(f, "invalid LUT: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for RsframeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RsframeError::Io(err) => Some(err),
+            RsframeError::ImageDecode(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for RsframeError {
+    fn from(err: io::Error) -> Self {
+        RsframeError::Io(err)
+    }
+}
+
+impl From<image::ImageError> for RsframeError {
+    fn from(err: image::ImageError) -> Self {
+        match err {
+            image::ImageError::IoError(err) => RsframeError::Io(err),
+            err => RsframeError::ImageDecode(err),
+        }
+    }
+}
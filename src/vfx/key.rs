@@ -0,0 +1,162 @@
+use crate::vfx::color::LUMA;
+use crate::vfx::video::Pixel;
+use rayon::prelude::*;
+use std::f32::consts::TAU;
+
+/// How `ChromaKey` measures how far a colour is from the key colour.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeySpace {
+    Rgb,   // The biggest difference in red, green or blue, which is what `draw_with_chroma_key` uses
+    Hsv,   // The difference in hue and saturation, ignoring brightness, which copes with unevenly lit screens
+    #[default]
+    YCbCr, // The difference in the colour channels of video, ignoring brightness like broadcast keyers do
+}
+
+/// A keyer for removing green and blue screens. Pixels close to the key colour become transparent, pixels between the inner
+/// and outer tolerance become partly transparent for soft edges, and the key colour that spills onto the foreground is removed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChromaKey {
+    pub color: Pixel,     // The colour of the screen
+    pub space: KeySpace,  // How the distance to the screen colour is measured
+    pub inner: f32,       // Colours closer than this are fully transparent, from 0 to 1
+    pub outer: f32,       // Colours further than this are fully opaque, from 0 to 1
+    pub despill: f32,     // How much of the screen colour is removed from the foreground, from 0 (none) to 1 (all of it)
+}
+
+impl ChromaKey {
+    /// Creates a keyer for a screen of the given colour, measuring in `KeySpace::YCbCr` with a soft edge and full despill.
+    /// Sample the colour from the footage, since real screens are rarely pure green or blue.
+    pub fn new(color: Pixel) -> ChromaKey {
+        ChromaKey {
+            color,
+            space: KeySpace::YCbCr,
+            inner: 0.1,
+            outer: 0.2,
+            despill: 1.0,
+        }
+    }
+
+    /// Sets how the distance to the screen colour is measured.
+    pub fn space(mut self, space: KeySpace) -> ChromaKey {
+        self.space = space;
+        self
+    }
+
+    /// Sets the distances, from 0 to 1, below which colours are fully transparent and above which they are fully opaque.
+    /// Colours in between fade from one to the other. Raise `inner` if bits of the screen are left, and widen the gap for softer edges.
+    pub fn tolerance(mut self, inner: f32, outer: f32) -> ChromaKey {
+        self.inner = inner.clamp(0.0, 1.0);
+        self.outer = outer.clamp(self.inner, 1.0);
+        self
+    }
+
+    /// Sets how much of the screen colour is removed from the foreground, which gets rid of the green or blue fringes around hair and edges.
+    /// Lower it if colours near the screen's, such as yellows in front of a green screen, shift too much.
+    pub fn despill(mut self, despill: f32) -> ChromaKey {
+        self.despill = despill.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Returns how far a colour is from the screen colour, from 0 (the same) to 1.
+    pub fn distance(&self, pixel: Pixel) -> f32 {
+        match self.space {
+            KeySpace::Rgb => {
+                let difference = |a: u8, b: u8| (a as i16 - b as i16).unsigned_abs();
+                let largest = difference(pixel.r, self.color.r)
+                    .max(difference(pixel.g, self.color.g))
+                    .max(difference(pixel.b, self.color.b));
+                largest as f32 / 255.0
+            }
+            KeySpace::Hsv => {
+                let (a, b) = (hue_plane(pixel), hue_plane(self.color));
+                ((a[0] - b[0]).hypot(a[1] - b[1]) / 2.0).min(1.0)
+            }
+            KeySpace::YCbCr => {
+                let (a, b) = (chroma(pixel), chroma(self.color));
+                (a[0] - b[0]).hypot(a[1] - b[1]).min(1.0)
+            }
+        }
+    }
+
+    /// Returns how opaque a pixel of the foreground is, from 0 (the screen) to 1 (the foreground), ignoring its own alpha.
+    pub fn matte(&self, pixel: Pixel) -> f32 {
+        let distance = self.distance(pixel);
+        if distance <= self.inner {
+            return 0.0;
+        }
+        if distance >= self.outer {
+            return 1.0;
+        }
+        let t = (distance - self.inner) / (self.outer - self.inner);
+        t * t * (3.0 - 2.0 * t)
+    }
+
+    /// Keys a pixel, making it as transparent as the matte and removing the spill.
+    ///
+    /// # Returns
+    ///
+    /// The keyed pixel, which keeps any transparency it already had.
+    pub fn apply(&self, pixel: Pixel) -> Pixel {
+        let alpha = (self.matte(pixel) * pixel.a as f32).round() as u8;
+        if alpha == 0 {
+            return Pixel::transparent();
+        }
+
+        let mut rgb = [pixel.r, pixel.g, pixel.b];
+        if self.despill > 0.0 {
+            // Limit the screen's strongest channel to the average of the other two, which removes its tint but not other colours
+            let key = [self.color.r, self.color.g, self.color.b];
+            let channel = (0..3).max_by_key(|&i| key[i]).unwrap_or(1);
+            let others = (0..3).filter(|&i| i != channel).map(|i| rgb[i] as f32).sum::<f32>() / 2.0;
+            let value = rgb[channel] as f32;
+            if value > others {
+                rgb[channel] = (value - (value - others) * self.despill).round() as u8;
+            }
+        }
+        Pixel::rgba(rgb[0], rgb[1], rgb[2], alpha)
+    }
+}
+
+/// Private function; the Cb and Cr channels of a colour with the Rec. 709 weights, each from -0.5 to 0.5.
+fn chroma(pixel: Pixel) -> [f32; 2] {
+    let [r, g, b] = [pixel.r, pixel.g, pixel.b].map(|value| value as f32 / 255.0);
+    let y = LUMA[0] * r + LUMA[1] * g + LUMA[2] * b;
+    [(b - y) / (2.0 * (1.0 - LUMA[2])), (r - y) / (2.0 * (1.0 - LUMA[0]))]
+}
+
+/// Private function; places a colour on the HSV colour wheel, with the hue as the angle and the saturation as the distance from the middle.
+fn hue_plane(pixel: Pixel) -> [f32; 2] {
+    let [r, g, b] = [pixel.r, pixel.g, pixel.b].map(|value| value as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let range = max - r.min(g).min(b);
+    if range <= 0.0 {
+        return [0.0, 0.0];
+    }
+
+    let hue = if max == r {
+        ((g - b) / range).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / range + 2.0
+    } else {
+        (r - g) / range + 4.0
+    } / 6.0;
+    let saturation = range / max;
+    let (sin, cos) = (hue * TAU).sin_cos();
+    [saturation * cos, saturation * sin]
+}
+
+/// Private function; keys every pixel of a frame.
+pub(crate) fn key(pixels: &mut [Pixel], key: &ChromaKey) {
+    pixels.par_iter_mut().for_each(|pixel| *pixel = key.apply(*pixel));
+}
+
+/// Private function; the matte of a frame as opaque greys, white where the foreground is kept and black where the screen is removed.
+pub(crate) fn matte(pixels: &[Pixel], key: &ChromaKey) -> Vec<Pixel> {
+    pixels
+        .par_iter()
+        .map(|pixel| {
+            let value = (key.matte(*pixel) * pixel.a as f32).round() as u8;
+            Pixel::new(value, value, value)
+        })
+        .collect()
+}
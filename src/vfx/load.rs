@@ -0,0 +1,279 @@
+use crate::vfx::error::RsframeError;
+use crate::vfx::progress::Monitor;
+use crate::vfx::video::FrameRate;
+use std::time::Duration;
+
+/// A position in a video, either as a timestamp or as a frame index.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Position {
+    Time(Duration), // A timestamp from the start of the video
+    Frame(usize),   // A frame index, counting from zero
+}
+
+impl Position {
+    /// Creates a position from a number of seconds. Negative seconds are treated as zero.
+    pub fn seconds(seconds: f64) -> Position {
+        Position::Time(Duration::from_secs_f64(seconds.max(0.0)))
+    }
+
+    /// Private function; converts the position to seconds at the given frame rate.
+    fn to_seconds(self, frame_rate: FrameRate) -> f64 {
+        match self {
+            Position::Time(time) => time.as_secs_f64(),
+            Position::Frame(frame) => frame as f64 / frame_rate.as_f64(),
+        }
+    }
+}
+
+/// Which part of a video `Video::from_file_with` decodes.
+/// The start is inclusive and the end is exclusive, so `start(Position::Frame(0)).end(Position::Frame(10))` loads ten frames.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoadOptions {
+    pub start: Option<Position>,   // Where to start decoding; the start of the video if not set
+    pub end: Option<Position>,     // Where to stop decoding; the end of the video if not set
+    pub stride: usize,             // Only every `stride`th frame is kept
+    pub max_frames: Option<usize>, // The most frames that will be decoded
+    pub monitor: Monitor,          // Receives the progress of the load and can cancel it
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        LoadOptions {
+            start: None,
+            end: None,
+            stride: 1,
+            max_frames: None,
+            monitor: Monitor::default(),
+        }
+    }
+}
+
+impl LoadOptions {
+    /// Creates options that load the whole video.
+    pub fn new() -> LoadOptions {
+        LoadOptions::default()
+    }
+
+    /// Starts decoding at the given position.
+    pub fn start(mut self, start: Position) -> LoadOptions {
+        self.start = Some(start);
+        self
+    }
+
+    /// Stops decoding right before the given position.
+    pub fn end(mut self, end: Position) -> LoadOptions {
+        self.end = Some(end);
+        self
+    }
+
+    /// Keeps only every `stride`th frame, such as one frame per second with a stride of the frame rate.
+    /// A stride of zero is treated as one.
+    pub fn stride(mut self, stride: usize) -> LoadOptions {
+        self.stride = stride.max(1);
+        self
+    }
+
+    /// Stops after the given number of frames has been decoded.
+    pub fn max_frames(mut self, max_frames: usize) -> LoadOptions {
+        self.max_frames = Some(max_frames);
+        self
+    }
+
+    /// Reports the progress of the load into `monitor`, which can also cancel it.
+    /// `Video::from_file_with` passes the monitor on to the loaded video for its bulk effects.
+    pub fn monitor(mut self, monitor: Monitor) -> LoadOptions {
+        self.monitor = monitor;
+        self
+    }
+
+    /// Returns the frame rate of the loaded frames; skipping frames with a stride slows the frame rate down to match.
+    pub fn frame_rate(&self, source: FrameRate) -> FrameRate {
+        FrameRate::new(source.numerator, source.denominator.saturating_mul(self.stride.max(1) as u32))
+    }
+
+    /// Returns the most frames these options can decode, if there is a limit.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_rate` - The frame rate of the source, used to turn timestamps into frames.
+    pub fn frame_limit(&self, frame_rate: FrameRate) -> Option<usize> {
+        let range = self.duration(frame_rate)
+            .map(|seconds| (seconds * frame_rate.as_f64()).round() as usize)
+            .map(|frames| frames.div_ceil(self.stride.max(1)));
+
+        match (range, self.max_frames) {
+            (Some(range), Some(max_frames)) => Some(range.min(max_frames)),
+            (range, max_frames) => range.or(max_frames),
+        }
+    }
+
+    /// Returns how many frames these options decode out of a video with `total_frames` frames.
+    ///
+    /// # Arguments
+    ///
+    /// * `total_frames` - The number of frames in the whole video.
+    /// * `frame_rate` - The frame rate of the source, used to turn timestamps into frames.
+    pub fn frame_count(&self, total_frames: usize, frame_rate: FrameRate) -> usize {
+        let start = self.start.map(|start| (start.to_seconds(frame_rate) * frame_rate.as_f64()).round() as usize).unwrap_or(0);
+        let remaining = total_frames.saturating_sub(start).div_ceil(self.stride.max(1));
+
+        match self.frame_limit(frame_rate) {
+            Some(limit) => remaining.min(limit),
+            None => remaining,
+        }
+    }
+
+    /// Private function; options that decode only the `index`th frame picked by these options.
+    pub(crate) fn nth(&self, index: usize, frame_rate: FrameRate) -> LoadOptions {
+        let start = self.start.map(|start| start.to_seconds(frame_rate)).unwrap_or(0.0);
+        let offset = (index * self.stride.max(1)) as f64 / frame_rate.as_f64();
+
+        // Seek half a frame early so rounding can never skip past the frame that was asked for
+        let seconds = start + offset - 0.5 / frame_rate.as_f64();
+        LoadOptions {
+            start: Some(Position::seconds(seconds)),
+            end: None,
+            stride: 1,
+            max_frames: Some(1),
+            monitor: Monitor::default(),
+        }
+    }
+
+    /// Private function; returns how many seconds to decode after the start, if there is an end.
+    fn duration(&self, frame_rate: FrameRate) -> Option<f64> {
+        let start = self.start.map(|start| start.to_seconds(frame_rate)).unwrap_or(0.0);
+        self.end.map(|end| (end.to_seconds(frame_rate) - start).max(0.0))
+    }
+
+    /// Checks that the end of the range comes after its start.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_rate` - The frame rate of the source, used to compare timestamps and frames.
+    ///
+    /// # Returns
+    ///
+    /// An `InvalidRange` error, in frames, if the range is empty.
+    pub fn validate(&self, frame_rate: FrameRate) -> Result<(), RsframeError> {
+        let to_frame = |position: Option<Position>| {
+            position.map(|position| (position.to_seconds(frame_rate) * frame_rate.as_f64()).round() as usize).unwrap_or(0)
+        };
+
+        match self.duration(frame_rate) {
+            Some(seconds) if seconds <= 0.0 => Err(RsframeError::InvalidRange {
+                start: to_frame(self.start),
+                end: to_frame(self.end),
+                length: to_frame(self.end),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the FFmpeg arguments that go before the input file; this seeks to the start.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_rate` - The frame rate of the source, used to turn frame indices into timestamps.
+    pub fn input_args(&self, frame_rate: FrameRate) -> Vec<String> {
+        let seconds = match self.start {
+            Some(Position::Time(time)) => time.as_secs_f64(),
+            // Seek half a frame early so rounding can never skip past the first frame
+            Some(Position::Frame(frame)) if frame > 0 => (frame as f64 - 0.5) / frame_rate.as_f64(),
+            _ => return Vec::new(),
+        };
+        vec!["-ss".to_string(), format!("{:.6}", seconds)]
+    }
+
+    /// Returns the FFmpeg arguments that go after the input file; these limit the length and skip frames.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_rate` - The frame rate of the source, used to turn frame indices into timestamps.
+    pub fn output_args(&self, frame_rate: FrameRate) -> Vec<String> {
+        let mut args = Vec::new();
+
+        // Timestamp ends are exact as a duration, frame ends are exact as a frame count
+        if let Some(Position::Time(_)) = self.end {
+            if let Some(seconds) = self.duration(frame_rate) {
+                args.extend(["-t".to_string(), format!("{:.6}", seconds)]);
+            }
+        }
+
+        if self.stride > 1 {
+            args.extend([
+                "-vf".to_string(), format!("select=not(mod(n\\,{}))", self.stride),
+                "-vsync".to_string(), "vfr".to_string(),
+            ]);
+        }
+
+        let frame_count = match self.end {
+            Some(Position::Frame(_)) => self.frame_limit(frame_rate),
+            _ => self.max_frames,
+        };
+        if let Some(frame_count) = frame_count {
+            args.extend(["-frames:v".to_string(), frame_count.to_string()]);
+        }
+
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FPS_25: FrameRate = FrameRate { numerator: 25, denominator: 1 };
+
+    #[test]
+    fn whole_video_needs_no_arguments() {
+        let options = LoadOptions::new();
+        assert!(options.input_args(FPS_25).is_empty());
+        assert!(options.output_args(FPS_25).is_empty());
+        assert_eq!(options.frame_count(100, FPS_25), 100);
+    }
+
+    #[test]
+    fn time_range_seeks_and_limits_the_duration() {
+        let options = LoadOptions::new()
+            .start(Position::seconds(1.5))
+            .end(Position::seconds(4.0));
+
+        assert_eq!(options.input_args(FPS_25), ["-ss", "1.500000"]);
+        assert_eq!(options.output_args(FPS_25), ["-t", "2.500000"]);
+        assert_eq!(options.frame_count(250, FPS_25), 63);
+    }
+
+    #[test]
+    fn frame_range_seeks_half_a_frame_early_and_counts_frames() {
+        let options = LoadOptions::new()
+            .start(Position::Frame(10))
+            .end(Position::Frame(20));
+
+        assert_eq!(options.input_args(FPS_25), ["-ss", "0.380000"]);
+        assert_eq!(options.output_args(FPS_25), ["-frames:v", "10"]);
+        assert_eq!(options.frame_count(100, FPS_25), 10);
+    }
+
+    #[test]
+    fn stride_selects_frames_and_slows_the_frame_rate() {
+        let options = LoadOptions::new().stride(5).max_frames(3);
+
+        assert_eq!(
+            options.output_args(FPS_25),
+            ["-vf", "select=not(mod(n\\,5))", "-vsync", "vfr", "-frames:v", "3"]
+        );
+        assert_eq!(options.frame_rate(FPS_25), FrameRate::from_fps(5));
+        assert_eq!(options.frame_count(100, FPS_25), 3);
+        assert_eq!(LoadOptions::new().stride(3).frame_count(10, FPS_25), 4);
+    }
+
+    #[test]
+    fn empty_ranges_are_rejected() {
+        let options = LoadOptions::new()
+            .start(Position::Frame(20))
+            .end(Position::Frame(10));
+
+        assert!(matches!(options.validate(FPS_25), Err(RsframeError::InvalidRange { start: 20, end: 10, .. })));
+        assert!(LoadOptions::new().end(Position::Frame(1)).validate(FPS_25).is_ok());
+    }
+}
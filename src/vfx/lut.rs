@@ -0,0 +1,437 @@
+use crate::vfx::color::Adjustment;
+use crate::vfx::error::RsframeError;
+use crate::vfx::video::Pixel;
+use rayon::prelude::*;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// How colours that fall between the entries of a 3D LUT are worked out.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Interpolation {
+    Trilinear,   // Blends the 8 entries around the colour
+    #[default]
+    Tetrahedral, // Blends the 4 entries of the tetrahedron around the colour, which keeps greys neutral and is what most grading tools use
+}
+
+/// A one-dimensional table that maps each channel on its own, used as a `.cube` file's 1D LUT or as the shaper in front of its 3D LUT.
+#[derive(Clone, Debug, PartialEq)]
+struct Table1D {
+    entries: Vec<[f32; 3]>, // The output for evenly spaced inputs
+    domain_min: [f32; 3],   // The input the first entry is for
+    domain_max: [f32; 3],   // The input the last entry is for
+}
+
+impl Table1D {
+    /// Private function; looks up each channel, interpolating linearly between entries.
+    fn apply(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let last = self.entries.len() - 1;
+        let mut out = [0.0; 3];
+        for channel in 0..3 {
+            let position = normalize(rgb[channel], self.domain_min[channel], self.domain_max[channel]) * last as f32;
+            let index = (position as usize).min(last - 1);
+            let fraction = position - index as f32;
+            let (low, high) = (self.entries[index][channel], self.entries[index + 1][channel]);
+            out[channel] = low + (high - low) * fraction;
+        }
+        out
+    }
+}
+
+/// A three-dimensional table that maps every colour to another colour.
+#[derive(Clone, Debug, PartialEq)]
+struct Table3D {
+    size: usize,            // The number of entries along each side
+    entries: Vec<[f32; 3]>, // The outputs, with red changing fastest and blue slowest, like in `.cube` files
+    domain_min: [f32; 3],   // The input the first entry along each side is for
+    domain_max: [f32; 3],   // The input the last entry along each side is for
+}
+
+impl Table3D {
+    /// Private function; the entry at the given red, green and blue indices.
+    fn entry(&self, r: usize, g: usize, b: usize) -> [f32; 3] {
+        self.entries[r + g * self.size + b * self.size * self.size]
+    }
+
+    /// Private function; looks up a colour, interpolating between the entries around it.
+    fn apply(&self, rgb: [f32; 3], interpolation: Interpolation) -> [f32; 3] {
+        let last = self.size - 1;
+        let mut index = [0; 3];
+        let mut fraction = [0.0; 3];
+        for channel in 0..3 {
+            let position = normalize(rgb[channel], self.domain_min[channel], self.domain_max[channel]) * last as f32;
+            index[channel] = (position as usize).min(last - 1);
+            fraction[channel] = position - index[channel] as f32;
+        }
+
+        let [r, g, b] = index;
+        let [fr, fg, fb] = fraction;
+        let corner = |dr: usize, dg: usize, db: usize| self.entry(r + dr, g + dg, b + db);
+        let c000 = corner(0, 0, 0);
+        let c111 = corner(1, 1, 1);
+
+        match interpolation {
+            Interpolation::Trilinear => {
+                let lerp = |a: [f32; 3], b: [f32; 3], t: f32| [0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * t);
+                let c00 = lerp(c000, corner(1, 0, 0), fr);
+                let c10 = lerp(corner(0, 1, 0), corner(1, 1, 0), fr);
+                let c01 = lerp(corner(0, 0, 1), corner(1, 0, 1), fr);
+                let c11 = lerp(corner(0, 1, 1), c111, fr);
+                lerp(lerp(c00, c10, fg), lerp(c01, c11, fg), fb)
+            }
+            Interpolation::Tetrahedral => {
+                // Pick the tetrahedron by the order of the fractions, then weight its four corners
+                let (weights, first, second) = if fr > fg {
+                    if fg > fb {
+                        ([1.0 - fr, fr - fg, fg - fb, fb], corner(1, 0, 0), corner(1, 1, 0))
+                    } else if fr > fb {
+                        ([1.0 - fr, fr - fb, fb - fg, fg], corner(1, 0, 0), corner(1, 0, 1))
+                    } else {
+                        ([1.0 - fb, fb - fr, fr - fg, fg], corner(0, 0, 1), corner(1, 0, 1))
+                    }
+                } else if fb > fg {
+                    ([1.0 - fb, fb - fg, fg - fr, fr], corner(0, 0, 1), corner(0, 1, 1))
+                } else if fb > fr {
+                    ([1.0 - fg, fg - fb, fb - fr, fr], corner(0, 1, 0), corner(0, 1, 1))
+                } else {
+                    ([1.0 - fg, fg - fr, fr - fb, fb], corner(0, 1, 0), corner(1, 1, 0))
+                };
+                [0, 1, 2].map(|i| weights[0] * c000[i] + weights[1] * first[i] + weights[2] * second[i] + weights[3] * c111[i])
+            }
+        }
+    }
+}
+
+/// Private function; where `value` lies between `min` and `max`, from 0 to 1.
+fn normalize(value: f32, min: f32, max: f32) -> f32 {
+    if max > min {
+        ((value - min) / (max - min)).clamp(0.0, 1.0)
+    } else {
+        0.0
+    }
+}
+
+/// A colour lookup table, such as a look delivered by a colourist as a `.cube` file.
+/// It can hold a 1D LUT, a 3D LUT or both, in which case the 1D LUT shapes the colours before the 3D LUT looks them up.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lut3D {
+    title: Option<String>,     // The `TITLE` of the file
+    shaper: Option<Table1D>,   // The 1D LUT, applied first
+    cube: Option<Table3D>,     // The 3D LUT
+}
+
+impl Lut3D {
+    /// Loads a `.cube` file in the Adobe or DaVinci Resolve format.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the `.cube` file.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `Lut3D`, or an `InvalidLut` error that says which line is wrong.
+    pub fn from_cube_file(path: impl AsRef<Path>) -> Result<Lut3D, RsframeError> {
+        Lut3D::from_cube(&fs::read_to_string(path)?)
+    }
+
+    /// Parses the contents of a `.cube` file. Both 1D and 3D LUTs of any size are supported, along with `DOMAIN_MIN`, `DOMAIN_MAX`
+    /// and Resolve's `LUT_1D_INPUT_RANGE` and `LUT_3D_INPUT_RANGE`. Unknown keywords are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The contents of the file.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `Lut3D`, or an `InvalidLut` error that says which line is wrong.
+    pub fn from_cube(text: &str) -> Result<Lut3D, RsframeError> {
+        let mut title = None;
+        let (mut size_1d, mut size_3d) = (None, None);
+        let (mut domain_min, mut domain_max) = ([0.0; 3], [1.0; 3]);
+        let (mut range_1d, mut range_3d) = (None, None);
+        let mut entries = Vec::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let invalid = |reason: &str| RsframeError::InvalidLut(format!("line {}: {}", number + 1, reason));
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let rest = rest.trim();
+            match keyword {
+                "TITLE" => title = Some(parse_title(rest).to_string()),
+                "LUT_1D_SIZE" => size_1d = Some(parse_size(rest, 2, 65536).ok_or_else(|| invalid("LUT_1D_SIZE must be from 2 to 65536"))?),
+                "LUT_3D_SIZE" => size_3d = Some(parse_size(rest, 2, 256).ok_or_else(|| invalid("LUT_3D_SIZE must be from 2 to 256"))?),
+                "DOMAIN_MIN" => domain_min = parse_triple(rest).ok_or_else(|| invalid("DOMAIN_MIN needs three numbers"))?,
+                "DOMAIN_MAX" => domain_max = parse_triple(rest).ok_or_else(|| invalid("DOMAIN_MAX needs three numbers"))?,
+                "LUT_1D_INPUT_RANGE" => range_1d = Some(parse_range(rest).ok_or_else(|| invalid("LUT_1D_INPUT_RANGE needs two numbers"))?),
+                "LUT_3D_INPUT_RANGE" => range_3d = Some(parse_range(rest).ok_or_else(|| invalid("LUT_3D_INPUT_RANGE needs two numbers"))?),
+                _ if keyword.starts_with(|c: char| c.is_ascii_alphabetic()) => {
+                    log::debug!(keyword = keyword; "skipping unknown .cube keyword {}", keyword);
+                }
+                _ => entries.push(parse_triple(line).ok_or_else(|| invalid("expected three numbers"))?),
+            }
+        }
+
+        let expected = size_1d.unwrap_or(0) + size_3d.map_or(0, |size| size * size * size);
+        if size_1d.is_none() && size_3d.is_none() {
+            return Err(RsframeError::InvalidLut("no LUT_1D_SIZE or LUT_3D_SIZE".to_string()));
+        }
+        if entries.len() != expected {
+            return Err(RsframeError::InvalidLut(format!("expected {} entries, found {}", expected, entries.len())));
+        }
+
+        let domain = |range: Option<(f32, f32)>| range.map_or((domain_min, domain_max), |(min, max)| ([min; 3], [max; 3]));
+        let cube_entries = entries.split_off(size_1d.unwrap_or(0));
+
+        let shaper = size_1d.map(|_| {
+            let (domain_min, domain_max) = domain(range_1d);
+            Table1D { entries, domain_min, domain_max }
+        });
+        let cube = size_3d.map(|size| {
+            let (domain_min, domain_max) = domain(range_3d);
+            Table3D { size, entries: cube_entries, domain_min, domain_max }
+        });
+
+        Ok(Lut3D { title, shaper, cube })
+    }
+
+    /// Bakes a chain of the crate's own colour adjustments into a 3D LUT, which can then be saved with `to_cube`
+    /// and loaded into other grading tools.
+    ///
+    /// # Arguments
+    ///
+    /// * `adjustments` - The adjustments to bake, in order.
+    /// * `size` - The number of entries along each side, from 2 to 256, such as 33. Larger LUTs are more accurate.
+    ///
+    /// # Returns
+    ///
+    /// The new `Lut3D`.
+    pub fn from_adjustments(adjustments: &[Adjustment], size: usize) -> Lut3D {
+        let size = size.clamp(2, 256);
+        let step = 1.0 / (size - 1) as f32;
+        let entries = (0..size * size * size)
+            .into_par_iter()
+            .map(|i| {
+                let rgb = [i % size, i / size % size, i / (size * size)].map(|index| index as f32 * step);
+                adjustments
+                    .iter()
+                    .fold(rgb, |rgb, adjustment| adjustment.apply(rgb))
+                    .map(|value| value.clamp(0.0, 1.0))
+            })
+            .collect();
+
+        Lut3D {
+            title: None,
+            shaper: None,
+            cube: Some(Table3D { size, entries, domain_min: [0.0; 3], domain_max: [1.0; 3] }),
+        }
+    }
+
+    /// Returns the `TITLE` of the LUT, if it has one.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// Sets the `TITLE` that `to_cube` writes.
+    pub fn with_title(mut self, title: &str) -> Lut3D {
+        self.title = Some(title.to_string());
+        self
+    }
+
+    /// Returns the number of entries along each side of the 3D LUT, or `None` if it only has a 1D LUT.
+    pub fn size(&self) -> Option<usize> {
+        self.cube.as_ref().map(|cube| cube.size)
+    }
+
+    /// Looks up a colour with channels from 0 to 1.
+    ///
+    /// # Arguments
+    ///
+    /// * `rgb` - The red, green and blue channels.
+    /// * `interpolation` - How colours between the entries of the 3D LUT are worked out.
+    ///
+    /// # Returns
+    ///
+    /// The looked up channels.
+    pub fn apply(&self, rgb: [f32; 3], interpolation: Interpolation) -> [f32; 3] {
+        let rgb = match &self.shaper {
+            Some(shaper) => shaper.apply(rgb),
+            None => rgb,
+        };
+        match &self.cube {
+            Some(cube) => cube.apply(rgb, interpolation),
+            None => rgb,
+        }
+    }
+
+    /// Writes the LUT in the `.cube` format.
+    ///
+    /// # Returns
+    ///
+    /// The contents of the `.cube` file.
+    pub fn to_cube(&self) -> String {
+        let mut text = String::new();
+        if let Some(title) = &self.title {
+            let _ = writeln!(text, "TITLE \"{}\"", title);
+        }
+
+        let triple = |text: &mut String, values: [f32; 3]| {
+            let _ = writeln!(text, "{:.6} {:.6} {:.6}", values[0], values[1], values[2]);
+        };
+
+        // A 1D LUT in front of a 3D LUT needs Resolve's keywords, which only support the same range on every channel
+        match (&self.shaper, &self.cube) {
+            (Some(shaper), Some(cube)) => {
+                let _ = writeln!(text, "LUT_1D_SIZE {}", shaper.entries.len());
+                let _ = writeln!(text, "LUT_1D_INPUT_RANGE {:.6} {:.6}", shaper.domain_min[0], shaper.domain_max[0]);
+                let _ = writeln!(text, "LUT_3D_SIZE {}", cube.size);
+                let _ = writeln!(text, "LUT_3D_INPUT_RANGE {:.6} {:.6}", cube.domain_min[0], cube.domain_max[0]);
+            }
+            (Some(shaper), None) => {
+                let _ = writeln!(text, "LUT_1D_SIZE {}", shaper.entries.len());
+                text.push_str("DOMAIN_MIN ");
+                triple(&mut text, shaper.domain_min);
+                text.push_str("DOMAIN_MAX ");
+                triple(&mut text, shaper.domain_max);
+            }
+            (None, Some(cube)) => {
+                let _ = writeln!(text, "LUT_3D_SIZE {}", cube.size);
+                text.push_str("DOMAIN_MIN ");
+                triple(&mut text, cube.domain_min);
+                text.push_str("DOMAIN_MAX ");
+                triple(&mut text, cube.domain_max);
+            }
+            (None, None) => {}
+        }
+
+        text.push('\n');
+        for entry in self.shaper.iter().flat_map(|shaper| &shaper.entries).chain(self.cube.iter().flat_map(|cube| &cube.entries)) {
+            triple(&mut text, *entry);
+        }
+        text
+    }
+
+    /// Saves the LUT as a `.cube` file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Where to save the file.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating whether the file was written.
+    pub fn save_cube(&self, path: impl AsRef<Path>) -> Result<(), RsframeError> {
+        fs::write(path, self.to_cube())?;
+        Ok(())
+    }
+}
+
+/// Private function; cuts a `#` comment off the end of a line, unless the `#` is inside a quoted string such as a title.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Private function; the title of a `TITLE` line, everything between its quotes, or the whole text if it isn't quoted.
+fn parse_title(text: &str) -> &str {
+    match text.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next().unwrap_or(""),
+        None => text,
+    }
+}
+
+/// Private function; parses a LUT size between `min` and `max`.
+fn parse_size(text: &str, min: usize, max: usize) -> Option<usize> {
+    text.parse().ok().filter(|size| (min..=max).contains(size))
+}
+
+/// Private function; parses three whitespace separated numbers.
+fn parse_triple(text: &str) -> Option<[f32; 3]> {
+    let mut numbers = text.split_whitespace().map(|number| number.parse::<f32>());
+    let triple = [numbers.next()?.ok()?, numbers.next()?.ok()?, numbers.next()?.ok()?];
+    if numbers.next().is_some() {
+        return None;
+    }
+    Some(triple)
+}
+
+/// Private function; parses two whitespace separated numbers.
+fn parse_range(text: &str) -> Option<(f32, f32)> {
+    let mut numbers = text.split_whitespace().map(|number| number.parse::<f32>());
+    let range = (numbers.next()?.ok()?, numbers.next()?.ok()?);
+    if numbers.next().is_some() {
+        return None;
+    }
+    Some(range)
+}
+
+/// Private function; looks up every pixel in the LUT and mixes the result in by `intensity`. Alpha is left as it is.
+pub(crate) fn apply(pixels: &mut [Pixel], lut: &Lut3D, interpolation: Interpolation, intensity: f32) {
+    let intensity = intensity.clamp(0.0, 1.0);
+    if intensity == 0.0 {
+        return;
+    }
+
+    pixels.par_iter_mut().for_each(|pixel| {
+        let rgb = [pixel.r, pixel.g, pixel.b].map(|value| value as f32 / 255.0);
+        let graded = lut.apply(rgb, interpolation);
+        let [r, g, b] = [0, 1, 2].map(|i| ((rgb[i] + (graded[i] - rgb[i]) * intensity).clamp(0.0, 1.0) * 255.0).round() as u8);
+        *pixel = Pixel::rgba(r, g, b, pixel.a);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IDENTITY_2: &str = "LUT_3D_SIZE 2\n0 0 0\n1 0 0\n0 1 0\n1 1 0\n0 0 1\n1 0 1\n0 1 1\n1 1 1\n";
+
+    #[test]
+    fn keeps_hashes_inside_quoted_titles() {
+        let lut = Lut3D::from_cube(&format!("TITLE \"a # b\" # a comment\n{}", IDENTITY_2)).unwrap();
+        assert_eq!(lut.title(), Some("a # b"));
+    }
+
+    #[test]
+    fn strips_comments_outside_quotes() {
+        assert_eq!(strip_comment("0.5 0.5 0.5 # grey"), "0.5 0.5 0.5 ");
+        assert_eq!(strip_comment("TITLE \"x#y\""), "TITLE \"x#y\"");
+        assert_eq!(parse_title("Plain"), "Plain");
+        assert_eq!(parse_title("\"Unclosed #1"), "Unclosed #1");
+    }
+
+    #[test]
+    fn identity_leaves_colours_alone() {
+        let lut = Lut3D::from_cube(IDENTITY_2).unwrap();
+        for interpolation in [Interpolation::Trilinear, Interpolation::Tetrahedral] {
+            let [r, g, b] = lut.apply([0.2, 0.5, 0.9], interpolation);
+            assert!((r - 0.2).abs() < 1e-5 && (g - 0.5).abs() < 1e-5 && (b - 0.9).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn reports_the_wrong_line() {
+        let err = Lut3D::from_cube("LUT_3D_SIZE 2\n0 0 0\n1 0\n").unwrap_err();
+        assert!(err.to_string().contains("line 3"), "{}", err);
+        assert!(matches!(Lut3D::from_cube("0 0 0\n"), Err(RsframeError::InvalidLut(_))));
+        assert!(matches!(Lut3D::from_cube("LUT_3D_SIZE 2\n0 0 0\n"), Err(RsframeError::InvalidLut(_))));
+    }
+
+    #[test]
+    fn round_trips_through_cube_text() {
+        let lut = Lut3D::from_cube(IDENTITY_2).unwrap().with_title("Teal #2");
+        let parsed = Lut3D::from_cube(&lut.to_cube()).unwrap();
+        assert_eq!(parsed.title(), Some("Teal #2"));
+        assert_eq!(parsed.size(), lut.size());
+    }
+}
@@ -1,8 +1,40 @@
-/// Small module for creating and handling temporary folders and names.
-mod tmp;
+/// Temporary folders that clean up after themselves.
+pub mod tmp;
+/// Error type shared by every `rsframe` API.
+pub mod error;
+/// Finds and checks ffmpeg and ffprobe.
+pub mod tools;
+/// Spawns external tools (ffmpeg, ffprobe) the same way on every platform.
+mod process;
 /// Core library for converting frames into a video.
-mod compile;
-/// Text managing module.
-mod text;
+pub mod compile;
+/// Codec, quality and container settings for encoding videos.
+pub mod encode;
+/// Progress reporting and cancellation for long operations.
+pub mod progress;
+/// Streams decoded frames out of ffmpeg.
+mod decode;
+/// Reads information about media files with ffprobe.
+pub mod probe;
+/// Options for decoding only part of a video.
+pub mod load;
+/// Lazy access to frames, for videos too large to hold in memory.
+pub mod source;
+/// Fonts and text rendering.
+pub mod text;
+/// Blend modes for layering frames over each other.
+pub mod blend;
+/// Filters for scaling frames.
+pub mod resize;
+/// Rotations, flips and affine transforms.
+pub mod transform;
+/// Convolution filters, such as blurs and sharpening.
+pub mod convolve;
+/// Colour correction, such as contrast, saturation and curves.
+pub mod color;
+/// Colour lookup tables, such as `.cube` files.
+pub mod lut;
+/// Chroma keying, for removing green and blue screens.
+pub mod key;
 /// Main interface for editing videos using `rsframe`
 pub mod video;
\ No newline at end of file
@@ -0,0 +1,275 @@
+use crate::vfx::error::RsframeError;
+use crate::vfx::process::{command, run};
+use crate::vfx::tools::Tools;
+use crate::vfx::video::FrameRate;
+use serde_json::Value;
+
+/// Everything ffprobe reports about a media file that `rsframe` cares about.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MediaInfo {
+    pub format: String,                  // The container format, such as `mov,mp4,m4a,3gp,3g2,mj2`
+    pub duration: Option<f64>,           // The duration of the whole file, in seconds
+    pub bit_rate: Option<u64>,           // The overall bitrate of the file, in bits per second
+    pub video_streams: Vec<VideoStream>, // Every video stream, in file order
+    pub audio_streams: Vec<AudioStream>, // Every audio stream, in file order
+}
+
+/// A single video stream of a media file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VideoStream {
+    pub index: usize,                    // The index of the stream in the file
+    pub codec: String,                   // The codec of the stream, such as `h264`
+    pub pixel_format: Option<String>,    // The pixel format of the stream, such as `yuv420p`
+    pub width: usize,                    // The stored width of the frames, before rotation
+    pub height: usize,                   // The stored height of the frames, before rotation
+    pub frame_rate: Option<FrameRate>,   // The average frame rate of the stream
+    pub frame_count: Option<usize>,      // The number of frames, if the container stores it
+    pub duration: Option<f64>,           // The duration of the stream, in seconds, or of the whole file if the container doesn't store it
+    pub rotation: i32,                   // How far the stream is turned clockwise when played, in degrees; decoded frames are already upright
+}
+
+/// A single audio stream of a media file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AudioStream {
+    pub index: usize,                    // The index of the stream in the file
+    pub codec: String,                   // The codec of the stream, such as `aac`
+    pub sample_rate: Option<u32>,        // The sample rate, in hertz
+    pub channels: Option<u32>,           // The number of audio channels
+    pub channel_layout: Option<String>,  // The channel layout, such as `stereo`
+    pub duration: Option<f64>,           // The duration of the stream, in seconds, or of the whole file if the container doesn't store it
+}
+
+impl MediaInfo {
+    /// Returns the first video stream, which is the one `Video::from_file` decodes.
+    pub fn video(&self) -> Option<&VideoStream> {
+        self.video_streams.first()
+    }
+
+    /// Returns whether the file has any audio.
+    pub fn has_audio(&self) -> bool {
+        !self.audio_streams.is_empty()
+    }
+}
+
+impl VideoStream {
+    /// Returns the size of the frames as they are decoded and played, with the rotation applied.
+    ///
+    /// # Returns
+    ///
+    /// The width and height of the displayed frames.
+    pub fn display_size(&self) -> (usize, usize) {
+        if self.rotation.rem_euclid(180) == 90 {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        }
+    }
+
+    /// Returns the number of frames in the stream. When the container does not store it,
+    /// it is estimated from the duration and frame rate.
+    pub fn estimated_frames(&self) -> Option<usize> {
+        self.frame_count.or_else(|| {
+            let seconds = self.duration?;
+            let frame_rate = self.frame_rate?;
+            Some((seconds * frame_rate.as_f64()).round() as usize)
+        })
+    }
+}
+
+/// Reads information about a media file with ffprobe.
+///
+/// # Arguments
+///
+/// * `filename` - The path to the media file.
+/// * `tools` - The tools to probe with, such as `Tools::global()`.
+///
+/// # Returns
+///
+/// The `MediaInfo` of the file, `ToolNotFound` without ffprobe, `ToolFailed` if ffprobe cannot read the file,
+/// or `InvalidMedia` if its output makes no sense.
+pub fn probe(filename: &str, tools: &Tools) -> Result<MediaInfo, RsframeError> {
+    let output = run(command(tools.ffprobe()?)
+        .args(["-v", "error", "-print_format", "json", "-show_format", "-show_streams"])
+        .arg(filename))?;
+
+    let json: Value = serde_json::from_slice(&output.stdout)
+        .map_err(|err| RsframeError::InvalidMedia(format!("could not parse ffprobe output: {}", err)))?;
+
+    parse_media_info(&json)
+}
+
+/// Private function; builds a `MediaInfo` out of ffprobe's JSON output.
+fn parse_media_info(json: &Value) -> Result<MediaInfo, RsframeError> {
+    let format = &json["format"];
+    let streams = json["streams"]
+        .as_array()
+        .ok_or_else(|| RsframeError::InvalidMedia("ffprobe did not list any streams".to_string()))?;
+
+    let file_duration = number(&format["duration"]);
+    let mut video_streams = Vec::new();
+    let mut audio_streams = Vec::new();
+
+    for stream in streams {
+        let index = stream["index"].as_u64().unwrap_or(0) as usize;
+        let codec = stream["codec_name"].as_str().unwrap_or("unknown").to_string();
+        // Matroska and WebM only store the duration of a stream in its tags, if at all
+        let duration = number(&stream["duration"])
+            .or_else(|| stream["tags"]["DURATION"].as_str().and_then(timestamp))
+            .or(file_duration);
+
+        match stream["codec_type"].as_str() {
+            Some("video") => {
+                // Cover art is stored as a single-frame video stream, it isn't part of the video
+                if stream["disposition"]["attached_pic"].as_u64() == Some(1) {
+                    continue;
+                }
+
+                let frame_rate = stream["avg_frame_rate"].as_str().and_then(FrameRate::parse)
+                    .or_else(|| stream["r_frame_rate"].as_str().and_then(FrameRate::parse));
+
+                video_streams.push(VideoStream {
+                    index,
+                    codec,
+                    pixel_format: stream["pix_fmt"].as_str().map(str::to_string),
+                    width: stream["width"].as_u64().unwrap_or(0) as usize,
+                    height: stream["height"].as_u64().unwrap_or(0) as usize,
+                    frame_rate,
+                    frame_count: number(&stream["nb_frames"]).map(|frames| frames as usize),
+                    duration,
+                    rotation: rotation(stream),
+                });
+            }
+            Some("audio") => audio_streams.push(AudioStream {
+                index,
+                codec,
+                sample_rate: number(&stream["sample_rate"]).map(|rate| rate as u32),
+                channels: stream["channels"].as_u64().map(|channels| channels as u32),
+                channel_layout: stream["channel_layout"].as_str().map(str::to_string),
+                duration,
+            }),
+            _ => {}
+        }
+    }
+
+    Ok(MediaInfo {
+        format: format["format_name"].as_str().unwrap_or("unknown").to_string(),
+        duration: file_duration,
+        bit_rate: number(&format["bit_rate"]).map(|rate| rate as u64),
+        video_streams,
+        audio_streams,
+    })
+}
+
+/// Private function; reads a number that ffprobe may have written as a JSON number or as a string.
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(text) => text.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Private function; reads a timestamp such as `00:01:02.500000000`, the way Matroska tags store durations, in seconds.
+fn timestamp(text: &str) -> Option<f64> {
+    let mut seconds = 0.0;
+    for part in text.trim().split(':') {
+        seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+    }
+    Some(seconds)
+}
+
+/// Private function; finds the clockwise rotation of a stream from its display matrix or its legacy `rotate` tag.
+fn rotation(stream: &Value) -> i32 {
+    // The display matrix turns anticlockwise and the `rotate` tag clockwise, so the matrix is negated like ffmpeg does
+    let side_data = stream["side_data_list"]
+        .as_array()
+        .and_then(|list| list.iter().find_map(|data| number(&data["rotation"])))
+        .map(|degrees| -degrees);
+
+    side_data
+        .or_else(|| number(&stream["tags"]["rotate"]))
+        .map(|degrees| degrees.round() as i32)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_mp4_streams() {
+        let info = parse_media_info(&json!({
+            "format": { "format_name": "mov,mp4,m4a,3gp,3g2,mj2", "duration": "10.000000", "bit_rate": "1200000" },
+            "streams": [
+                {
+                    "index": 0, "codec_type": "video", "codec_name": "h264", "pix_fmt": "yuv420p",
+                    "width": 1920, "height": 1080, "avg_frame_rate": "30000/1001", "nb_frames": "300", "duration": "10.010000"
+                },
+                { "index": 1, "codec_type": "audio", "codec_name": "aac", "sample_rate": "48000", "channels": 2, "channel_layout": "stereo" },
+                { "index": 2, "codec_type": "video", "codec_name": "mjpeg", "disposition": { "attached_pic": 1 } }
+            ]
+        })).unwrap();
+
+        assert_eq!(info.format, "mov,mp4,m4a,3gp,3g2,mj2");
+        assert_eq!(info.duration, Some(10.0));
+        assert_eq!(info.bit_rate, Some(1_200_000));
+        assert_eq!(info.video_streams.len(), 1);
+
+        let video = info.video().unwrap();
+        assert_eq!((video.width, video.height), (1920, 1080));
+        assert_eq!(video.frame_rate, Some(FrameRate::new(30000, 1001)));
+        assert_eq!(video.frame_count, Some(300));
+        assert_eq!(video.estimated_frames(), Some(300));
+
+        let audio = &info.audio_streams[0];
+        assert_eq!(audio.sample_rate, Some(48000));
+        assert_eq!(audio.channels, Some(2));
+        assert_eq!(audio.duration, Some(10.0));
+    }
+
+    #[test]
+    fn estimates_matroska_frames_from_tags() {
+        let info = parse_media_info(&json!({
+            "format": { "format_name": "matroska,webm", "duration": "4.000000" },
+            "streams": [
+                { "index": 0, "codec_type": "video", "codec_name": "ffv1", "avg_frame_rate": "25/1", "tags": { "DURATION": "00:00:02.000000000" } }
+            ]
+        })).unwrap();
+
+        let video = info.video().unwrap();
+        assert_eq!(video.duration, Some(2.0));
+        assert_eq!(video.estimated_frames(), Some(50));
+    }
+
+    #[test]
+    fn estimates_frames_from_file_duration() {
+        let info = parse_media_info(&json!({
+            "format": { "format_name": "matroska,webm", "duration": "4.000000" },
+            "streams": [{ "index": 0, "codec_type": "video", "codec_name": "vp9", "avg_frame_rate": "25/1" }]
+        })).unwrap();
+
+        assert_eq!(info.video().unwrap().estimated_frames(), Some(100));
+    }
+
+    #[test]
+    fn rotation_is_clockwise_from_either_source() {
+        let side_data = json!({ "side_data_list": [{ "side_data_type": "Display Matrix", "rotation": -90 }] });
+        let tag = json!({ "tags": { "rotate": "90" } });
+        assert_eq!(rotation(&side_data), 90);
+        assert_eq!(rotation(&tag), 90);
+        assert_eq!(rotation(&json!({})), 0);
+    }
+
+    #[test]
+    fn rejects_output_without_streams() {
+        assert!(matches!(parse_media_info(&json!({ "format": {} })), Err(RsframeError::InvalidMedia(_))));
+    }
+
+    #[test]
+    fn reads_timestamps() {
+        assert_eq!(timestamp("01:02:03.5"), Some(3723.5));
+        assert_eq!(timestamp("12.25"), Some(12.25));
+        assert_eq!(timestamp("N/A"), None);
+    }
+}
@@ -0,0 +1,180 @@
+use crate::vfx::error::RsframeError;
+use std::ffi::OsStr;
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::{Child, Command, Output, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+/// Windows process creation flag that stops a console window from opening for the child.
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// Creates a `Command` for an external tool such as ffmpeg or ffprobe.
+/// On Windows the console window is hidden, everywhere else the command is left untouched.
+///
+/// # Arguments
+///
+/// * `program` - The name or path of the program to run.
+///
+/// # Returns
+///
+/// A new `Command` ready to have arguments added.
+pub fn command<S: AsRef<OsStr>>(program: S) -> Command {
+    #[allow(unused_mut)]
+    let mut command = Command::new(program);
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    command
+}
+
+/// Runs a command to completion and captures its output.
+///
+/// # Arguments
+///
+/// * `command` - The command to run.
+///
+/// # Returns
+///
+/// The captured `Output`, or `ToolNotFound`/`ToolFailed` if the tool is missing or exits unsuccessfully.
+pub fn run(command: &mut Command) -> Result<Output, RsframeError> {
+    let tool = command.get_program().to_string_lossy().to_string();
+    log::debug!(tool = tool.as_str(); "running {}", command_line(command));
+
+    let started = Instant::now();
+    let output = command.output().map_err(|err| spawn_error(&tool, err))?;
+    let elapsed = started.elapsed();
+
+    if output.status.success() {
+        log::debug!(tool = tool.as_str(), elapsed:? = elapsed; "{} finished in {:.2?}", tool, elapsed);
+        Ok(output)
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        log::warn!(tool = tool.as_str(), status:% = output.status, elapsed:? = elapsed; "{} failed: {}", tool, stderr.trim());
+        Err(RsframeError::ToolFailed { tool, stderr })
+    }
+}
+
+/// Private function; formats a command the way it would be typed into a shell, for logging.
+fn command_line(command: &Command) -> String {
+    let mut line = command.get_program().to_string_lossy().to_string();
+    for arg in command.get_args() {
+        let arg = arg.to_string_lossy();
+        if arg.is_empty() || arg.contains(char::is_whitespace) {
+            line.push_str(&format!(" \"{}\"", arg));
+        } else {
+            line.push(' ');
+            line.push_str(&arg);
+        }
+    }
+    line
+}
+
+/// Converts an error from starting a tool into an `RsframeError`.
+pub fn spawn_error(tool: &str, err: io::Error) -> RsframeError {
+    log::warn!(tool = tool; "could not start {}: {}", tool, err);
+    if err.kind() == io::ErrorKind::NotFound {
+        RsframeError::ToolNotFound { tool: tool.to_string() }
+    } else {
+        RsframeError::Io(err)
+    }
+}
+
+/// A tool that was started in the background with `spawn`.
+pub struct Running {
+    pub child: Child,                     // The running process, with its stdin/stdout pipes
+    tool: String,                         // The program that was started
+    stderr: Option<JoinHandle<String>>,   // The thread collecting everything written to stderr
+    started: Instant,                     // When the tool was started
+}
+
+impl Running {
+    /// Waits for the tool to exit.
+    ///
+    /// # Returns
+    ///
+    /// `ToolFailed`, with the captured stderr, if the tool exits unsuccessfully.
+    pub fn wait(mut self) -> Result<(), RsframeError> {
+        // Close stdin first so tools reading from it see the end of their input
+        drop(self.child.stdin.take());
+
+        let status = self.child.wait()?;
+        let stderr = self.stderr.take()
+            .and_then(|reader| reader.join().ok())
+            .unwrap_or_default();
+
+        let elapsed = self.started.elapsed();
+        if status.success() {
+            log::debug!(tool = self.tool.as_str(), elapsed:? = elapsed; "{} finished in {:.2?}", self.tool, elapsed);
+            Ok(())
+        } else {
+            log::warn!(tool = self.tool.as_str(), status:% = status, elapsed:? = elapsed; "{} failed: {}", self.tool, stderr.trim());
+            Err(RsframeError::ToolFailed { tool: self.tool, stderr })
+        }
+    }
+
+    /// Stops the tool without waiting for it to finish its work, such as when its output is no longer needed.
+    pub fn stop(mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        log::debug!(tool = self.tool.as_str(); "stopped {}", self.tool);
+    }
+}
+
+/// Starts a command in the background. Its stderr is collected on another thread so the pipe never fills up,
+/// while stdin and stdout are left as the caller configured them.
+///
+/// # Arguments
+///
+/// * `command` - The command to start.
+///
+/// # Returns
+///
+/// The `Running` tool, or `ToolNotFound` if it could not be started.
+pub fn spawn(command: &mut Command) -> Result<Running, RsframeError> {
+    let tool = command.get_program().to_string_lossy().to_string();
+    log::debug!(tool = tool.as_str(); "starting {}", command_line(command));
+
+    let mut child = command
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| spawn_error(&tool, err))?;
+
+    let stderr = child.stderr.take().map(|mut pipe| {
+        thread::spawn(move || {
+            let mut buffer = String::new();
+            let _ = pipe.read_to_string(&mut buffer);
+            buffer
+        })
+    });
+
+    Ok(Running { child, tool, stderr, started: Instant::now() })
+}
+
+/// Finds the path of a tool that ships next to another one, such as ffprobe next to ffmpeg.
+///
+/// # Arguments
+///
+/// * `program` - The name or path of the known tool.
+/// * `sibling` - The name of the tool to look for.
+///
+/// # Returns
+///
+/// `sibling` in the same folder as `program`, or just `sibling` if `program` is looked up on PATH.
+pub fn sibling_tool(program: &str, sibling: &str) -> String {
+    let path = Path::new(program);
+    let name = match path.extension() {
+        Some(ext) => format!("{}.{}", sibling, ext.to_string_lossy()),
+        None => sibling.to_string(),
+    };
+
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.join(name).to_string_lossy().to_string(),
+        _ => name,
+    }
+}
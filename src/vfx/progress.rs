@@ -0,0 +1,202 @@
+use crate::vfx::error::RsframeError;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Receives progress reports from long operations, such as saving, loading and bulk effects.
+/// Implement it to show progress in your own interface, or use `IndicatifProgress` for a terminal bar.
+pub trait Progress: Send + Sync {
+    /// Called when an operation starts.
+    ///
+    /// # Arguments
+    ///
+    /// * `task` - What is being done, such as `"Saving"`.
+    /// * `total` - The number of steps the operation takes, usually frames, if it is known.
+    fn start(&self, task: &str, total: Option<u64>);
+
+    /// Called when the operation has taken `steps` more steps.
+    fn advance(&self, steps: u64);
+
+    /// Called with a status update that isn't a step, such as waiting for ffmpeg to finish.
+    fn message(&self, message: &str);
+
+    /// Called when the operation has stopped, whether it succeeded, failed or was cancelled.
+    fn finish(&self);
+}
+
+/// A `Progress` that ignores every report. This is the default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoProgress;
+
+impl Progress for NoProgress {
+    fn start(&self, _task: &str, _total: Option<u64>) {}
+
+    fn advance(&self, _steps: u64) {}
+
+    fn message(&self, _message: &str) {}
+
+    fn finish(&self) {}
+}
+
+/// A `Progress` that draws an `indicatif` progress bar in the terminal.
+pub struct IndicatifProgress {
+    bar: ProgressBar, // The bar that is drawn
+}
+
+impl IndicatifProgress {
+    /// Creates a progress bar with the default style, showing the elapsed time, the task and the number of frames done.
+    pub fn new() -> IndicatifProgress {
+        IndicatifProgress::with_style(ProgressStyle::default_bar()
+            .template("[{elapsed_precise}] {msg} {bar:40.cyan/blue} {pos}/{len} frames")
+            .unwrap()
+            .progress_chars("=> "))
+    }
+
+    /// Creates a progress bar with a custom `indicatif` style.
+    pub fn with_style(style: ProgressStyle) -> IndicatifProgress {
+        let bar = ProgressBar::hidden();
+        bar.set_style(style);
+        IndicatifProgress { bar }
+    }
+}
+
+impl Default for IndicatifProgress {
+    fn default() -> Self {
+        IndicatifProgress::new()
+    }
+}
+
+impl Progress for IndicatifProgress {
+    fn start(&self, task: &str, total: Option<u64>) {
+        self.bar.reset();
+        self.bar.set_length(total.unwrap_or(0));
+        self.bar.set_message(task.to_string());
+        self.bar.set_draw_target(indicatif::ProgressDrawTarget::stderr());
+    }
+
+    fn advance(&self, steps: u64) {
+        self.bar.inc(steps);
+    }
+
+    fn message(&self, message: &str) {
+        self.bar.set_message(message.to_string());
+    }
+
+    fn finish(&self) {
+        self.bar.finish();
+    }
+}
+
+/// A flag that aborts a running operation once it is set. Clones share the same flag,
+/// so a token can be handed to another thread, such as a GUI's cancel button, and cancelled from there.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>, // Whether the operation should stop
+}
+
+impl CancelToken {
+    /// Creates a token that has not been cancelled.
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    /// Asks every operation using this token to stop as soon as it can.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns whether `cancel` has been called.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Checks whether the operation should stop.
+    ///
+    /// # Returns
+    ///
+    /// A `Cancelled` error if `cancel` has been called.
+    pub fn check(&self) -> Result<(), RsframeError> {
+        if self.is_cancelled() {
+            Err(RsframeError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl PartialEq for CancelToken {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.cancelled, &other.cancelled)
+    }
+}
+
+/// Where an operation reports its progress and how it can be cancelled.
+/// `EncodeOptions` and `LoadOptions` carry one for saving and loading, and `Video::set_monitor` sets one for bulk effects.
+#[derive(Clone)]
+pub struct Monitor {
+    pub progress: Arc<dyn Progress>, // Receives the progress reports
+    pub cancel: CancelToken,         // Stops the operation once cancelled
+}
+
+impl Monitor {
+    /// Creates a monitor that reports into `progress` and can't be cancelled until `cancel_token` is set.
+    pub fn new(progress: impl Progress + 'static) -> Monitor {
+        Monitor {
+            progress: Arc::new(progress),
+            cancel: CancelToken::new(),
+        }
+    }
+
+    /// Creates a monitor that draws an `indicatif` progress bar in the terminal.
+    pub fn indicatif() -> Monitor {
+        Monitor::new(IndicatifProgress::new())
+    }
+
+    /// Stops operations once the given token is cancelled.
+    pub fn cancel_token(mut self, cancel: CancelToken) -> Monitor {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Private function; checks for cancellation and then reports one step.
+    pub(crate) fn step(&self) -> Result<(), RsframeError> {
+        self.cancel.check()?;
+        self.progress.advance(1);
+        Ok(())
+    }
+
+    /// Private function; runs `task` between `start` and `finish` reports, so `finish` is reported even when it fails.
+    pub(crate) fn run<T>(
+        &self,
+        task: &str,
+        total: Option<u64>,
+        work: impl FnOnce() -> Result<T, RsframeError>,
+    ) -> Result<T, RsframeError> {
+        self.cancel.check()?;
+        self.progress.start(task, total);
+        let result = work();
+        self.progress.finish();
+        result
+    }
+}
+
+impl Default for Monitor {
+    fn default() -> Self {
+        Monitor::new(NoProgress)
+    }
+}
+
+impl fmt::Debug for Monitor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Monitor")
+            .field("cancel", &self.cancel)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for Monitor {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.progress, &other.progress) && self.cancel == other.cancel
+    }
+}
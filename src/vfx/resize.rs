@@ -0,0 +1,296 @@
+use crate::vfx::video::Pixel;
+use rayon::prelude::*;
+use std::f32::consts::PI;
+
+/// The filter used to work out the colour of each pixel when scaling a frame.
+/// They are listed from fastest and blockiest to slowest and sharpest.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Filter {
+    Nearest,  // Copies the closest pixel, which keeps hard edges, such as for pixel art
+    Bilinear, // Blends the nearest pixels linearly
+    Bicubic,  // Blends a 4x4 area with a Catmull-Rom curve, which is sharper than bilinear
+    #[default]
+    Lanczos3, // Blends a 6x6 area with a windowed sinc, which is the sharpest and the default
+}
+
+impl Filter {
+    /// Private function; how far from its centre the filter reaches, in source pixels when enlarging.
+    pub(crate) fn support(self) -> f32 {
+        match self {
+            Filter::Nearest => 0.5,
+            Filter::Bilinear => 1.0,
+            Filter::Bicubic => 2.0,
+            Filter::Lanczos3 => 3.0,
+        }
+    }
+
+    /// Private function; the weight of a pixel `x` pixels away from the centre of the filter.
+    pub(crate) fn weight(self, x: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            Filter::Nearest => if x < 0.5 { 1.0 } else { 0.0 },
+            Filter::Bilinear => (1.0 - x).max(0.0),
+            Filter::Bicubic => {
+                // Catmull-Rom, the cubic with a = -0.5
+                if x < 1.0 {
+                    (1.5 * x - 2.5) * x * x + 1.0
+                } else if x < 2.0 {
+                    ((-0.5 * x + 2.5) * x - 4.0) * x + 2.0
+                } else {
+                    0.0
+                }
+            }
+            Filter::Lanczos3 => if x < 3.0 { sinc(x) * sinc(x / 3.0) } else { 0.0 },
+        }
+    }
+}
+
+/// Private function; the normalised sinc function.
+fn sinc(x: f32) -> f32 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// How `Frame::resize_with` and `Video::resize_with` scale frames.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ResizeOptions {
+    pub filter: Filter,       // The filter each pixel is worked out with
+    pub gamma_correct: bool,  // Whether to filter in linear light instead of on the sRGB values
+}
+
+impl ResizeOptions {
+    /// Creates options that scale with Lanczos3 on the sRGB values.
+    pub fn new() -> ResizeOptions {
+        ResizeOptions::default()
+    }
+
+    /// Sets the filter each pixel is worked out with.
+    pub fn filter(mut self, filter: Filter) -> ResizeOptions {
+        self.filter = filter;
+        self
+    }
+
+    /// Converts colours to linear light before filtering and back afterwards. This keeps the brightness of fine detail,
+    /// such as thin bright lines on a dark background, when downscaling, but is slower.
+    pub fn gamma_correct(mut self, gamma_correct: bool) -> ResizeOptions {
+        self.gamma_correct = gamma_correct;
+        self
+    }
+}
+
+/// How `Frame::fit` and `Video::fit` make a frame fill a new resolution.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FitMode {
+    Contain, // Scales the frame to fit inside, then pads the rest with the background (letterbox or pillarbox)
+    Cover,   // Scales the frame to fill everything, then crops what sticks out
+    Stretch, // Scales the frame to exactly the new resolution, ignoring the aspect ratio
+    Pad,     // Keeps the frame's size, padding it with the background or cropping it where it doesn't fit
+}
+
+/// Which part of the frame is kept in place when it is padded or cropped, as fractions of the free space.
+/// `Anchor::new(0.0, 0.0)` is the top left corner, `Anchor::new(1.0, 1.0)` the bottom right and `Anchor::CENTER` the middle.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Anchor {
+    pub x: f32, // From 0 (left) to 1 (right)
+    pub y: f32, // From 0 (top) to 1 (bottom)
+}
+
+impl Anchor {
+    pub const TOP_LEFT: Anchor = Anchor { x: 0.0, y: 0.0 };
+    pub const TOP: Anchor = Anchor { x: 0.5, y: 0.0 };
+    pub const TOP_RIGHT: Anchor = Anchor { x: 1.0, y: 0.0 };
+    pub const LEFT: Anchor = Anchor { x: 0.0, y: 0.5 };
+    pub const CENTER: Anchor = Anchor { x: 0.5, y: 0.5 };
+    pub const RIGHT: Anchor = Anchor { x: 1.0, y: 0.5 };
+    pub const BOTTOM_LEFT: Anchor = Anchor { x: 0.0, y: 1.0 };
+    pub const BOTTOM: Anchor = Anchor { x: 0.5, y: 1.0 };
+    pub const BOTTOM_RIGHT: Anchor = Anchor { x: 1.0, y: 1.0 };
+
+    /// Creates an anchor at any point, such as `Anchor::new(0.5, 0.3)` to keep a face a little above the middle in view.
+    /// Values are clamped between 0 and 1.
+    pub fn new(x: f32, y: f32) -> Anchor {
+        Anchor {
+            x: x.clamp(0.0, 1.0),
+            y: y.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Private function; where the top left corner of a `width` by `height` frame goes on a `target_width` by `target_height` canvas.
+    /// It is negative on the sides where the frame is cropped.
+    pub(crate) fn offset(self, width: usize, height: usize, target_width: usize, target_height: usize) -> (i64, i64) {
+        let along = |size: usize, target: usize, fraction: f32| ((target as f32 - size as f32) * fraction).round() as i64;
+        (along(width, target_width, self.x), along(height, target_height, self.y))
+    }
+}
+
+impl Default for Anchor {
+    /// The middle of the frame.
+    fn default() -> Self {
+        Anchor::CENTER
+    }
+}
+
+/// Private function; the size a `width` by `height` frame is scaled to before it is padded or cropped to `target_width` by `target_height`.
+pub(crate) fn fitted_size(width: usize, height: usize, target_width: usize, target_height: usize, mode: FitMode) -> (usize, usize) {
+    if width == 0 || height == 0 {
+        return (target_width, target_height);
+    }
+
+    let scale_x = target_width as f64 / width as f64;
+    let scale_y = target_height as f64 / height as f64;
+    let scale = match mode {
+        FitMode::Stretch => return (target_width, target_height),
+        FitMode::Pad => return (width, height),
+        FitMode::Contain => scale_x.min(scale_y),
+        FitMode::Cover => scale_x.max(scale_y),
+    };
+
+    let scaled = |side: usize| ((side as f64 * scale).round() as usize).max(1);
+    (scaled(width), scaled(height))
+}
+
+/// The source pixels and weights that make up one destination pixel.
+struct Contribution {
+    start: usize,      // The first source pixel
+    weights: Vec<f32>, // The weight of each source pixel from `start` on, adding up to one
+}
+
+/// Private function; works out which source pixels make up each of the `dst` pixels along one axis of `src` pixels.
+fn contributions(src: usize, dst: usize, filter: Filter) -> Vec<Contribution> {
+    let scale = src as f32 / dst as f32;
+
+    if filter == Filter::Nearest {
+        return (0..dst)
+            .map(|i| Contribution {
+                start: (((i as f32 + 0.5) * scale) as usize).min(src - 1),
+                weights: vec![1.0],
+            })
+            .collect();
+    }
+
+    // When shrinking, the filter is stretched over the source pixels so every one of them counts
+    let stretch = scale.max(1.0);
+    let support = filter.support() * stretch;
+
+    (0..dst)
+        .map(|i| {
+            let center = (i as f32 + 0.5) * scale;
+            let start = (center - support).floor().max(0.0) as usize;
+            let end = ((center + support).ceil() as usize).min(src);
+
+            let mut weights: Vec<f32> = (start..end)
+                .map(|j| filter.weight((j as f32 + 0.5 - center) / stretch))
+                .collect();
+            let total: f32 = weights.iter().sum();
+            if total != 0.0 {
+                weights.iter_mut().for_each(|weight| *weight /= total);
+            }
+
+            Contribution { start, weights }
+        })
+        .collect()
+}
+
+/// Private function; scales `pixels`, a `width` by `height` image stored row by row, to `new_width` by `new_height`.
+/// Colours are filtered premultiplied by their alpha, so transparent pixels don't bleed their colour into the edges.
+pub(crate) fn resample(
+    pixels: &[Pixel],
+    width: usize,
+    height: usize,
+    new_width: usize,
+    new_height: usize,
+    options: &ResizeOptions,
+) -> Vec<Pixel> {
+    if new_width == 0 || new_height == 0 || width == 0 || height == 0 {
+        return vec![Pixel::transparent(); new_width * new_height];
+    }
+
+    let to_linear: Vec<f32> = (0..=255u8)
+        .map(|value| {
+            let value = value as f32 / 255.0;
+            if options.gamma_correct { srgb_to_linear(value) } else { value }
+        })
+        .collect();
+
+    let source: Vec<[f32; 4]> = pixels
+        .par_iter()
+        .map(|pixel| {
+            let alpha = pixel.a as f32 / 255.0;
+            [
+                to_linear[pixel.r as usize] * alpha,
+                to_linear[pixel.g as usize] * alpha,
+                to_linear[pixel.b as usize] * alpha,
+                alpha,
+            ]
+        })
+        .collect();
+
+    // Scale the rows first and then the columns, which is much faster than filtering both at once
+    let columns = contributions(width, new_width, options.filter);
+    let mut wide = vec![[0.0; 4]; new_width * height];
+    wide.par_chunks_mut(new_width).enumerate().for_each(|(y, row)| {
+        let source_row = &source[y * width..(y + 1) * width];
+        for (out, contribution) in row.iter_mut().zip(&columns) {
+            *out = accumulate(contribution, |j| source_row[j]);
+        }
+    });
+
+    let rows = contributions(height, new_height, options.filter);
+    let mut scaled = vec![Pixel::transparent(); new_width * new_height];
+    scaled.par_chunks_mut(new_width).zip(rows.par_iter()).for_each(|(row, contribution)| {
+        for (x, out) in row.iter_mut().enumerate() {
+            let [r, g, b, a] = accumulate(contribution, |j| wide[j * new_width + x]);
+            *out = from_premultiplied(r, g, b, a, options.gamma_correct);
+        }
+    });
+
+    scaled
+}
+
+/// Private function; adds up the weighted source pixels of one contribution.
+fn accumulate(contribution: &Contribution, sample: impl Fn(usize) -> [f32; 4]) -> [f32; 4] {
+    let mut sum = [0.0; 4];
+    for (offset, weight) in contribution.weights.iter().enumerate() {
+        let value = sample(contribution.start + offset);
+        for channel in 0..4 {
+            sum[channel] += value[channel] * weight;
+        }
+    }
+    sum
+}
+
+/// Private function; turns filtered, premultiplied channels back into a pixel.
+fn from_premultiplied(r: f32, g: f32, b: f32, a: f32, gamma_correct: bool) -> Pixel {
+    let a = a.clamp(0.0, 1.0);
+    if a <= 0.0 {
+        return Pixel::transparent();
+    }
+
+    let channel = |value: f32| {
+        let value = (value / a).clamp(0.0, 1.0);
+        let value = if gamma_correct { linear_to_srgb(value) } else { value };
+        (value * 255.0).round() as u8
+    };
+    Pixel::rgba(channel(r), channel(g), channel(b), (a * 255.0).round() as u8)
+}
+
+/// Private function; converts an sRGB channel from 0 to 1 into linear light.
+pub(crate) fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Private function; converts a linear light channel from 0 to 1 back into sRGB.
+pub(crate) fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
@@ -0,0 +1,227 @@
+use crate::vfx::decode::FrameReader;
+use crate::vfx::error::RsframeError;
+use crate::vfx::load::LoadOptions;
+use crate::vfx::probe::probe;
+use crate::vfx::tools::Tools;
+use crate::vfx::video::{Frame, FrameRate, Video};
+use std::path::PathBuf;
+
+/// Anything that can hand out the frames of a video one at a time, such as a `Video` in memory
+/// or a `FileSource` that decodes frames from disk on demand.
+/// `compile::save_source` can save any source, so edits can stream from the input file to the output file.
+pub trait FrameSource {
+    /// Returns the number of frames in the source.
+    fn len(&self) -> usize;
+
+    /// Returns the width of every frame.
+    fn width(&self) -> usize;
+
+    /// Returns the height of every frame.
+    fn height(&self) -> usize;
+
+    /// Returns the frame rate of the source.
+    fn frame_rate(&self) -> FrameRate;
+
+    /// Returns a copy of the frame at the given index.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the frame.
+    ///
+    /// # Returns
+    ///
+    /// The frame, or an `InvalidRange` error if the index is past the end.
+    fn get(&self, index: usize) -> Result<Frame, RsframeError>;
+
+    /// Returns whether the source has no frames.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns an iterator over every frame, in order. Sources that decode from a file should override this
+    /// to read the file once instead of seeking for every frame.
+    fn frames(&self) -> Box<dyn Iterator<Item = Result<Frame, RsframeError>> + '_> {
+        Box::new((0..self.len()).map(move |index| self.get(index)))
+    }
+
+    /// Applies an effect to every frame as it is read, without decoding the whole source first.
+    /// The effect is given the index of each frame, so it can be limited to a range of frames.
+    ///
+    /// # Arguments
+    ///
+    /// * `effect` - The function applied to each frame, such as `|_, frame| frame.monochrome()`.
+    ///
+    /// # Returns
+    ///
+    /// A new `FrameSource` with the effect applied.
+    fn map_frames<F>(self, effect: F) -> MapFrames<Self, F>
+    where
+        Self: Sized,
+        F: Fn(usize, &mut Frame),
+    {
+        MapFrames { source: self, effect }
+    }
+}
+
+impl FrameSource for Video {
+    fn len(&self) -> usize {
+        self.length()
+    }
+
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn frame_rate(&self) -> FrameRate {
+        self.frame_rate
+    }
+
+    fn get(&self, index: usize) -> Result<Frame, RsframeError> {
+        if index >= self.length() {
+            return Err(RsframeError::InvalidRange { start: index, end: index, length: self.length() });
+        }
+        Ok(self.get_frame(index).clone())
+    }
+}
+
+/// A `FrameSource` that applies an effect to the frames of another source. Created with `FrameSource::map_frames`.
+pub struct MapFrames<S, F> {
+    source: S, // The source the frames come from
+    effect: F, // The effect applied to each frame
+}
+
+impl<S: FrameSource, F: Fn(usize, &mut Frame)> FrameSource for MapFrames<S, F> {
+    fn len(&self) -> usize {
+        self.source.len()
+    }
+
+    fn width(&self) -> usize {
+        self.source.width()
+    }
+
+    fn height(&self) -> usize {
+        self.source.height()
+    }
+
+    fn frame_rate(&self) -> FrameRate {
+        self.source.frame_rate()
+    }
+
+    fn get(&self, index: usize) -> Result<Frame, RsframeError> {
+        let mut frame = self.source.get(index)?;
+        (self.effect)(index, &mut frame);
+        Ok(frame)
+    }
+
+    fn frames(&self) -> Box<dyn Iterator<Item = Result<Frame, RsframeError>> + '_> {
+        Box::new(self.source.frames().enumerate().map(move |(index, frame)| {
+            let mut frame = frame?;
+            (self.effect)(index, &mut frame);
+            Ok(frame)
+        }))
+    }
+}
+
+/// A `FrameSource` that decodes frames from a video file on demand, so videos far larger than memory can be edited.
+/// `frames` streams the file through a single FFmpeg process, while `get` seeks to one frame at a time.
+pub struct FileSource {
+    filename: String,      // The path to the video file
+    ffmpeg: PathBuf,       // The ffmpeg executable
+    options: LoadOptions,  // Which frames of the file are part of the source
+    width: usize,          // The width of the decoded frames
+    height: usize,         // The height of the decoded frames
+    file_rate: FrameRate,  // The frame rate of the file itself
+    length: usize,         // The number of frames, as estimated by ffprobe
+}
+
+impl FileSource {
+    /// Opens a video file as a source. Nothing is decoded until frames are asked for.
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - The path to the video file.
+    /// * `tools` - The tools to decode with, such as `Tools::global()`. ffprobe is required.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the new `FileSource`, or an error if ffprobe cannot read the file.
+    pub fn open(filename: &str, tools: &Tools) -> Result<FileSource, RsframeError> {
+        FileSource::open_with(filename, &LoadOptions::default(), tools)
+    }
+
+    /// Opens part of a video file as a source, such as a time range or every Nth frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - The path to the video file.
+    /// * `options` - Which frames of the file are part of the source.
+    /// * `tools` - The tools to decode with, such as `Tools::global()`. ffprobe is required.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the new `FileSource`, or an error if ffprobe cannot read the file.
+    pub fn open_with(filename: &str, options: &LoadOptions, tools: &Tools) -> Result<FileSource, RsframeError> {
+        let info = probe(filename, tools)?;
+        let stream = info.video()
+            .ok_or_else(|| RsframeError::InvalidMedia(format!("{} has no video stream", filename)))?;
+
+        let (width, height) = stream.display_size();
+        if width == 0 || height == 0 {
+            return Err(RsframeError::InvalidMedia(format!("{} has a video stream without a size", filename)));
+        }
+
+        let file_rate = stream.frame_rate.unwrap_or_default();
+        options.validate(file_rate)?;
+
+        Ok(FileSource {
+            filename: filename.to_string(),
+            ffmpeg: tools.ffmpeg.clone(),
+            options: options.clone(),
+            width,
+            height,
+            file_rate,
+            length: options.frame_count(stream.estimated_frames().unwrap_or(0), file_rate),
+        })
+    }
+}
+
+impl FrameSource for FileSource {
+    /// Returns the number of frames, as estimated from the file's metadata.
+    /// Files without an exact frame count may yield slightly more or fewer frames from `frames`.
+    fn len(&self) -> usize {
+        self.length
+    }
+
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn frame_rate(&self) -> FrameRate {
+        self.options.frame_rate(self.file_rate)
+    }
+
+    fn get(&self, index: usize) -> Result<Frame, RsframeError> {
+        if index >= self.length {
+            return Err(RsframeError::InvalidRange { start: index, end: index, length: self.length });
+        }
+
+        let options = self.options.nth(index, self.file_rate);
+        let mut reader = FrameReader::open(&self.filename, self.width, self.height, &options, self.file_rate, &self.ffmpeg)?;
+        reader.next().unwrap_or(Err(RsframeError::NoFrames))
+    }
+
+    fn frames(&self) -> Box<dyn Iterator<Item = Result<Frame, RsframeError>> + '_> {
+        match FrameReader::open(&self.filename, self.width, self.height, &self.options, self.file_rate, &self.ffmpeg) {
+            Ok(reader) => Box::new(reader),
+            Err(err) => Box::new(std::iter::once(Err(err))),
+        }
+    }
+}
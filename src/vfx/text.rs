@@ -1,36 +1,469 @@
-use std::os::windows::process::CommandExt;
-use std::process::Command;
-use crate::vfx::tmp::rng_string;
-
-pub fn create_text(text: String, font: String, color: String, width: usize, height: usize, magick: &str) -> String {
-    let img_name = format!("{}.bmp", rng_string(32));
-    let output = Command::new(magick)
-        .creation_flags(0x08000000)
-        .args(&[
-            "-background",
-            "transparent",
-            "-font",
-            font.as_str(),
-            "-size",
-            format!("{}x{}",width,height).as_str(),
-            "-fill",
-            color.as_str(),
-            "-gravity",
-            "center",
-            format!("label:{}", text).as_str(),
-            img_name.as_str(),
-        ])
-        .output()
-        .expect("Failed to execute ImageMagick command");
-
-    if output.status.success() {
-        println!("Image successfully created: output.png");
-    } else {
-        eprintln!(
-            "Error: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
-    }
-
-    img_name
-}
\ No newline at end of file
+use crate::vfx::error::RsframeError;
+use crate::vfx::video::{Frame, Pixel};
+use ab_glyph::{point, Font as _, FontArc, GlyphId, PxScale, PxScaleFont, ScaleFont};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// A TrueType or OpenType font that text is rendered with. Cloning a font is cheap, the font data is shared.
+#[derive(Clone)]
+pub struct Font {
+    font: FontArc, // The parsed font
+}
+
+impl Font {
+    /// Loads a `.ttf` or `.otf` font file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the font file.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `Font`, or an `InvalidFont` error if the file is not a font.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Font, RsframeError> {
+        Font::from_bytes(fs::read(path)?)
+    }
+
+    /// Loads a font from the bytes of a `.ttf` or `.otf` file, such as one embedded with `include_bytes!`.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The contents of the font file.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `Font`, or an `InvalidFont` error if the bytes are not a font.
+    pub fn from_bytes(bytes: impl Into<Vec<u8>>) -> Result<Font, RsframeError> {
+        FontArc::try_from_vec(bytes.into())
+            .map(|font| Font { font })
+            .map_err(|_| RsframeError::InvalidFont("not a TrueType or OpenType font".to_string()))
+    }
+}
+
+impl fmt::Debug for Font {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Font").finish_non_exhaustive()
+    }
+}
+
+/// How the lines of a text block line up with each other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    Left,    // Lines start at the left edge of the block
+    Center,  // Lines are centred in the block
+    Right,   // Lines end at the right edge of the block
+    Justify, // Lines are stretched to fill the block, except the last line of each paragraph
+}
+
+/// An outline drawn around every glyph.
+#[derive(Clone, Copy, Debug)]
+pub struct Stroke {
+    pub color: Pixel, // The colour of the outline
+    pub width: f32,   // How far the outline reaches out of the glyphs, in pixels
+}
+
+/// A shadow drawn behind the text.
+#[derive(Clone, Copy, Debug)]
+pub struct Shadow {
+    pub color: Pixel,  // The colour of the shadow
+    pub offset_x: f32, // How far right of the text the shadow falls, in pixels
+    pub offset_y: f32, // How far below the text the shadow falls, in pixels
+    pub blur: f32,     // How soft the edge of the shadow is, in pixels; 0 is a hard shadow
+}
+
+/// A solid box drawn behind the text.
+#[derive(Clone, Copy, Debug)]
+pub struct Background {
+    pub color: Pixel,  // The colour of the box
+    pub padding: f32,  // The space between the edge of the box and the text, in pixels
+}
+
+/// Everything about how text is laid out and drawn, for `Frame::draw_text`.
+/// Start with `TextStyle::new` and change what you need with the builder methods,
+/// for example `TextStyle::new(font, 48.0).max_width(600.0).align(Align::Center).shadow(4.0, 4.0, 3.0, black)`.
+#[derive(Clone, Debug)]
+pub struct TextStyle {
+    pub font: Font,                     // The font the text is drawn with
+    pub size: f32,                      // The height of the font, in pixels
+    pub color: Pixel,                   // The colour of the text
+    pub max_width: Option<f32>,         // Lines are wrapped between words to fit this width, in pixels
+    pub align: Align,                   // How lines line up with each other
+    pub line_height: f32,               // The distance between lines, as a multiple of the font's own line spacing
+    pub letter_spacing: f32,            // Extra space between characters, in pixels; negative values tighten the text
+    pub stroke: Option<Stroke>,         // An outline around the glyphs
+    pub shadow: Option<Shadow>,         // A shadow behind the text
+    pub background: Option<Background>, // A box behind the text
+}
+
+impl TextStyle {
+    /// Creates a style for white, left aligned text without wrapping, outline, shadow or background.
+    ///
+    /// # Arguments
+    ///
+    /// * `font` - The font the text is drawn with.
+    /// * `size` - The height of the font, in pixels.
+    pub fn new(font: Font, size: f32) -> TextStyle {
+        TextStyle {
+            font,
+            size,
+            color: Pixel::white(),
+            max_width: None,
+            align: Align::Left,
+            line_height: 1.0,
+            letter_spacing: 0.0,
+            stroke: None,
+            shadow: None,
+            background: None,
+        }
+    }
+
+    /// Sets the colour of the text.
+    pub fn color(mut self, color: Pixel) -> TextStyle {
+        self.color = color;
+        self
+    }
+
+    /// Wraps lines between words so that they fit in `max_width` pixels. Words wider than that are split between characters.
+    pub fn max_width(mut self, max_width: f32) -> TextStyle {
+        self.max_width = Some(max_width);
+        self
+    }
+
+    /// Sets how lines line up with each other.
+    pub fn align(mut self, align: Align) -> TextStyle {
+        self.align = align;
+        self
+    }
+
+    /// Sets the distance between lines as a multiple of the font's own line spacing, such as 1.5 for one and a half spacing.
+    pub fn line_height(mut self, line_height: f32) -> TextStyle {
+        self.line_height = line_height;
+        self
+    }
+
+    /// Adds extra space between characters, in pixels.
+    pub fn letter_spacing(mut self, letter_spacing: f32) -> TextStyle {
+        self.letter_spacing = letter_spacing;
+        self
+    }
+
+    /// Draws an outline of the given colour and width around every glyph.
+    pub fn stroke(mut self, color: Pixel, width: f32) -> TextStyle {
+        self.stroke = Some(Stroke { color, width });
+        self
+    }
+
+    /// Draws a shadow behind the text, moved by the offset and softened by `blur` pixels.
+    pub fn shadow(mut self, offset_x: f32, offset_y: f32, blur: f32, color: Pixel) -> TextStyle {
+        self.shadow = Some(Shadow { color, offset_x, offset_y, blur });
+        self
+    }
+
+    /// Draws a solid box behind the text, `padding` pixels larger than the text on every side.
+    pub fn background(mut self, color: Pixel, padding: f32) -> TextStyle {
+        self.background = Some(Background { color, padding });
+        self
+    }
+
+    /// Returns the size of the text when drawn with this style, including the background padding,
+    /// so text can be positioned before it is drawn. Outlines and shadows may reach past it.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text to measure.
+    ///
+    /// # Returns
+    ///
+    /// The width and height of the text, in pixels.
+    pub fn measure(&self, text: &str) -> (usize, usize) {
+        let layout = Layout::new(text, self);
+        let padding = self.padding() * 2.0;
+        ((layout.width + padding).ceil() as usize, (layout.height + padding).ceil() as usize)
+    }
+
+    /// Private function; returns the padding of the background box, or 0 without one.
+    fn padding(&self) -> f32 {
+        self.background.map(|background| background.padding.max(0.0)).unwrap_or(0.0)
+    }
+}
+
+/// Private struct; a single laid out line of text.
+struct Line {
+    glyphs: Vec<(char, GlyphId, f32)>, // Every character with its glyph and its x position from the start of the line
+    width: f32,                        // The width of the line
+    last: bool,                        // Whether this is the last line of a paragraph, which is never justified
+}
+
+/// Private struct; text broken into lines and measured.
+struct Layout {
+    lines: Vec<Line>,   // The lines, from top to bottom
+    width: f32,         // The width of the text block
+    height: f32,        // The height of the text block
+    line_advance: f32,  // The distance from one baseline to the next
+    ascent: f32,        // The distance from the top of a line to its baseline
+}
+
+impl Layout {
+    /// Private function; wraps `text` into lines that fit the style's `max_width` and measures them.
+    fn new(text: &str, style: &TextStyle) -> Layout {
+        let scaled = style.font.font.as_scaled(PxScale::from(style.size));
+        let line_advance = (scaled.ascent() - scaled.descent() + scaled.line_gap()) * style.line_height;
+
+        let mut lines = Vec::new();
+        for paragraph in text.lines() {
+            let wrapped = match style.max_width {
+                Some(max_width) => wrap(&scaled, paragraph, max_width, style.letter_spacing),
+                None => vec![paragraph.to_string()],
+            };
+
+            let count = wrapped.len();
+            for (index, line) in wrapped.into_iter().enumerate() {
+                lines.push(lay_out_line(&scaled, &line, style.letter_spacing, index + 1 == count));
+            }
+        }
+
+        let widest = lines.iter().map(|line| line.width).fold(0.0, f32::max);
+        let height = match lines.len() {
+            0 => 0.0,
+            count => line_advance * (count - 1) as f32 + scaled.ascent() - scaled.descent(),
+        };
+
+        Layout {
+            lines,
+            width: style.max_width.unwrap_or(widest),
+            height,
+            line_advance,
+            ascent: scaled.ascent(),
+        }
+    }
+}
+
+/// Private function; splits a paragraph into lines no wider than `max_width`, breaking between words where it can.
+fn wrap(scaled: &PxScaleFont<&FontArc>, paragraph: &str, max_width: f32, spacing: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+
+    for word in paragraph.split(' ') {
+        let candidate = if current.is_empty() { word.to_string() } else { format!("{} {}", current, word) };
+        if measure_line(scaled, &candidate, spacing) <= max_width {
+            current = candidate;
+            continue;
+        }
+
+        if !current.is_empty() {
+            lines.push(std::mem::take(&mut current));
+        }
+
+        // A word that is too wide on its own is split between characters
+        for character in word.chars() {
+            let candidate = format!("{}{}", current, character);
+            if !current.is_empty() && measure_line(scaled, &candidate, spacing) > max_width {
+                lines.push(std::mem::take(&mut current));
+                current.push(character);
+            } else {
+                current = candidate;
+            }
+        }
+    }
+
+    lines.push(current);
+    lines
+}
+
+/// Private function; returns how wide a line of text is, including kerning and letter spacing.
+fn measure_line(scaled: &PxScaleFont<&FontArc>, line: &str, spacing: f32) -> f32 {
+    lay_out_line(scaled, line, spacing, false).width
+}
+
+/// Private function; places every character of a line along the baseline.
+fn lay_out_line(scaled: &PxScaleFont<&FontArc>, line: &str, spacing: f32, last: bool) -> Line {
+    let mut glyphs = Vec::new();
+    let mut x = 0.0;
+    let mut previous = None;
+
+    for character in line.chars() {
+        let id = scaled.glyph_id(character);
+        if let Some(previous) = previous {
+            x += scaled.kern(previous, id) + spacing;
+        }
+        glyphs.push((character, id, x));
+        x += scaled.h_advance(id);
+        previous = Some(id);
+    }
+
+    Line { glyphs, width: x, last }
+}
+
+/// Private struct; how much of each pixel in a rectangle is covered by a shape, from 0 to 1.
+struct Mask {
+    width: usize,     // The width of the rectangle
+    height: usize,    // The height of the rectangle
+    values: Vec<f32>, // The coverage of every pixel, row by row
+}
+
+impl Mask {
+    /// Private function; creates an empty mask.
+    fn new(width: usize, height: usize) -> Mask {
+        Mask { width, height, values: vec![0.0; width * height] }
+    }
+
+    /// Private function; returns the coverage at the given coordinates, or 0 outside of the mask.
+    fn get(&self, x: i64, y: i64) -> f32 {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return 0.0;
+        }
+        self.values[y as usize * self.width + x as usize]
+    }
+
+    /// Private function; grows the shape by `radius` pixels in every direction, with an anti-aliased edge.
+    fn dilate(&self, radius: f32) -> Mask {
+        let reach = radius.ceil() as i64 + 1;
+        let mut dilated = Mask::new(self.width, self.height);
+
+        for y in 0..self.height as i64 {
+            for x in 0..self.width as i64 {
+                let mut coverage: f32 = 0.0;
+                for dy in -reach..=reach {
+                    for dx in -reach..=reach {
+                        let weight = (radius + 0.5 - ((dx * dx + dy * dy) as f32).sqrt()).clamp(0.0, 1.0);
+                        if weight > 0.0 {
+                            coverage = coverage.max(self.get(x + dx, y + dy) * weight);
+                        }
+                    }
+                }
+                dilated.values[y as usize * self.width + x as usize] = coverage;
+            }
+        }
+        dilated
+    }
+
+    /// Private function; softens the mask with three box blurs, which is close to a gaussian blur of `radius` pixels.
+    fn blur(&mut self, radius: f32) {
+        let radius = (radius / 3.0).round() as i64;
+        if radius < 1 {
+            return;
+        }
+
+        for _ in 0..3 {
+            self.box_blur(radius, 1, 0);
+            self.box_blur(radius, 0, 1);
+        }
+    }
+
+    /// Private function; averages every pixel with its `radius` neighbours in the direction (`dx`, `dy`).
+    fn box_blur(&mut self, radius: i64, dx: i64, dy: i64) {
+        let size = (radius * 2 + 1) as f32;
+        let mut blurred = vec![0.0; self.values.len()];
+
+        for y in 0..self.height as i64 {
+            for x in 0..self.width as i64 {
+                let sum: f32 = (-radius..=radius).map(|step| self.get(x + step * dx, y + step * dy)).sum();
+                blurred[y as usize * self.width + x as usize] = sum / size;
+            }
+        }
+        self.values = blurred;
+    }
+
+    /// Private function; blends `color` into `frame` by the coverage of each pixel, with the mask's corner at (`x`, `y`).
+    fn paint(&self, frame: &mut Frame, x: i64, y: i64, color: Pixel) {
+        for my in 0..self.height {
+            for mx in 0..self.width {
+                let coverage = self.values[my * self.width + mx];
+                let (fx, fy) = (x + mx as i64, y + my as i64);
+                if coverage <= 0.0 || fx < 0 || fy < 0 || fx >= frame.width as i64 || fy >= frame.height as i64 {
+                    continue;
+                }
+
+                let (fx, fy) = (fx as usize, fy as usize);
+                let under = frame.get_pixel(fx, fy);
+                frame.put_pixel(fx, fy, color.over(under, coverage));
+            }
+        }
+    }
+}
+
+/// Private function; draws `text` onto `frame` with its top left corner, including the background padding, at (`x`, `y`).
+pub(crate) fn draw(frame: &mut Frame, text: &str, x: f32, y: f32, style: &TextStyle) {
+    let layout = Layout::new(text, style);
+    let padding = style.padding();
+
+    if let Some(background) = style.background {
+        let width = (layout.width + padding * 2.0).ceil() as usize;
+        let height = (layout.height + padding * 2.0).ceil() as usize;
+        let mut mask = Mask::new(width, height);
+        mask.values.fill(1.0);
+        mask.paint(frame, x.round() as i64, y.round() as i64, background.color);
+    }
+
+    // Leave room around the glyphs for the outline and the blurred shadow
+    let stroke_width = style.stroke.map(|stroke| stroke.width.max(0.0)).unwrap_or(0.0);
+    let blur = style.shadow.map(|shadow| shadow.blur.max(0.0)).unwrap_or(0.0);
+    let margin = (stroke_width + blur).ceil() as usize + 2;
+
+    let fill = rasterize(&layout, style, margin);
+    let outline = style.stroke.map(|stroke| (fill.dilate(stroke.width.max(0.0)), stroke.color));
+
+    let left = (x + padding).floor() as i64 - margin as i64;
+    let top = (y + padding).floor() as i64 - margin as i64;
+
+    if let Some(shadow) = style.shadow {
+        let solid = outline.as_ref().map(|(mask, _)| mask).unwrap_or(&fill);
+        let mut shape = Mask { width: solid.width, height: solid.height, values: solid.values.clone() };
+        shape.blur(shadow.blur.max(0.0));
+        shape.paint(frame, left + shadow.offset_x.round() as i64, top + shadow.offset_y.round() as i64, shadow.color);
+    }
+
+    if let Some((mask, color)) = &outline {
+        mask.paint(frame, left, top, *color);
+    }
+    fill.paint(frame, left, top, style.color);
+}
+
+/// Private function; draws the glyphs of a layout into a mask, `margin` pixels in from its edges.
+fn rasterize(layout: &Layout, style: &TextStyle, margin: usize) -> Mask {
+    let scaled = style.font.font.as_scaled(PxScale::from(style.size));
+    let mut mask = Mask::new(
+        layout.width.max(0.0).ceil() as usize + margin * 2,
+        layout.height.max(0.0).ceil() as usize + margin * 2,
+    );
+
+    for (row, line) in layout.lines.iter().enumerate() {
+        let baseline = margin as f32 + layout.ascent + layout.line_advance * row as f32;
+        let free = (layout.width - line.width).max(0.0);
+        let spaces = line.glyphs.iter().filter(|(character, _, _)| *character == ' ').count();
+
+        let (start, gap) = match style.align {
+            Align::Left => (0.0, 0.0),
+            Align::Center => (free / 2.0, 0.0),
+            Align::Right => (free, 0.0),
+            Align::Justify if !line.last && spaces > 0 => (0.0, free / spaces as f32),
+            Align::Justify => (0.0, 0.0),
+        };
+
+        let mut stretch = 0.0;
+        for &(character, id, offset) in &line.glyphs {
+            if character == ' ' {
+                stretch += gap;
+            }
+
+            let position = point(margin as f32 + start + offset + stretch, baseline);
+            let Some(outline) = scaled.outline_glyph(id.with_scale_and_position(scaled.scale(), position)) else {
+                continue;
+            };
+
+            let bounds = outline.px_bounds();
+            outline.draw(|glyph_x, glyph_y, coverage| {
+                let mx = bounds.min.x as i64 + glyph_x as i64;
+                let my = bounds.min.y as i64 + glyph_y as i64;
+                if mx < 0 || my < 0 || mx >= mask.width as i64 || my >= mask.height as i64 {
+                    return;
+                }
+
+                let value = &mut mask.values[my as usize * mask.width + mx as usize];
+                *value = (*value + coverage).min(1.0);
+            });
+        }
+    }
+    mask
+}
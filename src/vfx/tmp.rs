@@ -1,6 +1,12 @@
 use rand::{thread_rng, Rng};
 use rand::distributions::Alphanumeric;
+use std::env;
 use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Environment variable that overrides the folder workspaces are created in.
+pub const TMPDIR_ENV: &str = "RSFRAME_TMPDIR";
 
 pub fn rng_string(length: u8) -> String {
     let random_string: String = thread_rng()
@@ -11,14 +17,75 @@ pub fn rng_string(length: u8) -> String {
     random_string
 }
 
-pub fn create_tmp_folder() -> String {
-    let id = rng_string(32);
-    let path = format!("_{}-tmp", id);
+/// A temporary folder for intermediate files, such as extracted frames.
+/// The folder and everything in it is removed when the workspace is dropped, even when an error or a panic cut the work short,
+/// unless `keep` was called.
+#[derive(Debug)]
+pub struct Workspace {
+    path: PathBuf, // The folder itself
+    keep: bool,    // Whether the folder outlives the workspace
+}
+
+impl Workspace {
+    /// Creates a workspace in the system's temporary folder, or in `RSFRAME_TMPDIR` if it is set.
+    ///
+    /// # Returns
+    ///
+    /// The new `Workspace`, or an error if the folder could not be created.
+    pub fn new() -> io::Result<Workspace> {
+        match env::var_os(TMPDIR_ENV) {
+            Some(root) if !root.is_empty() => Workspace::new_in(root),
+            _ => Workspace::new_in(env::temp_dir()),
+        }
+    }
+
+    /// Creates a workspace inside the given folder, creating the folder if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The folder to create the workspace in.
+    ///
+    /// # Returns
+    ///
+    /// The new `Workspace`, or an error if the folder could not be created.
+    pub fn new_in(root: impl AsRef<Path>) -> io::Result<Workspace> {
+        fs::create_dir_all(root.as_ref())?;
 
-    fs::create_dir(path.clone()).unwrap();
-    path
+        let path = root.as_ref().join(format!("rsframe-{}", rng_string(16)));
+        fs::create_dir(&path)?;
+        log::debug!(path:? = path; "created workspace {}", path.display());
+        Ok(Workspace { path, keep: false })
+    }
+
+    /// Returns the path of the folder.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Keeps the folder and its contents after the workspace is dropped, such as for debugging a render.
+    ///
+    /// # Returns
+    ///
+    /// The path of the kept folder.
+    pub fn keep(mut self) -> PathBuf {
+        self.keep = true;
+        log::info!(path:? = self.path; "keeping workspace {}", self.path.display());
+        self.path.clone()
+    }
+
+    /// Removes the folder now, returning any error instead of only logging it like dropping does.
+    pub fn close(mut self) -> io::Result<()> {
+        self.keep = true;
+        fs::remove_dir_all(&self.path)
+    }
 }
 
-pub fn drop_folder(folder: String) {
-    fs::remove_dir_all(folder).expect("Could not drop folder.");
-}
\ No newline at end of file
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        if !self.keep {
+            if let Err(err) = fs::remove_dir_all(&self.path) {
+                log::warn!(path:? = self.path; "could not remove workspace {}: {}", self.path.display(), err);
+            }
+        }
+    }
+}
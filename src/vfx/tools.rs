@@ -0,0 +1,183 @@
+use crate::vfx::error::RsframeError;
+use crate::vfx::process::{command, run, sibling_tool};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Environment variable that overrides where ffmpeg is found.
+pub const FFMPEG_ENV: &str = "RSFRAME_FFMPEG";
+/// Environment variable that overrides where ffprobe is found.
+pub const FFPROBE_ENV: &str = "RSFRAME_FFPROBE";
+
+/// The process-wide tools returned by `Tools::global`.
+static GLOBAL: OnceLock<Tools> = OnceLock::new();
+
+/// The external programs `rsframe` runs, found and checked once up front so that a misconfigured machine fails early with a clear error.
+/// ffmpeg is required; without ffprobe videos are decoded through temporary PNG images.
+#[derive(Clone, Debug)]
+pub struct Tools {
+    pub ffmpeg: PathBuf,                 // The ffmpeg executable
+    pub ffprobe: Option<PathBuf>,        // The ffprobe executable, if one was found
+    pub ffmpeg_version: String,          // The first line of `ffmpeg -version`
+    encoders: Vec<String>,               // The video encoders this ffmpeg was built with
+}
+
+impl Tools {
+    /// Finds the tools from the `RSFRAME_FFMPEG` and `RSFRAME_FFPROBE` environment variables,
+    /// falling back to PATH. Without `RSFRAME_FFPROBE`, the ffprobe next to ffmpeg is used before the one on PATH,
+    /// so a custom ffmpeg build is paired with its own ffprobe.
+    ///
+    /// # Returns
+    ///
+    /// The checked `Tools`, or `ToolNotFound`/`ToolFailed` if ffmpeg is missing or broken.
+    pub fn detect() -> Result<Tools, RsframeError> {
+        let ffmpeg = locate(FFMPEG_ENV, "ffmpeg")
+            .ok_or_else(|| RsframeError::ToolNotFound { tool: "ffmpeg".to_string() })?;
+
+        let ffprobe = from_env(FFPROBE_ENV)
+            .or_else(|| {
+                let sibling = PathBuf::from(sibling_tool(&ffmpeg.to_string_lossy(), "ffprobe"));
+                if sibling.is_file() { Some(sibling) } else { None }
+            })
+            .or_else(|| find_on_path("ffprobe"));
+
+        Tools::from_paths(ffmpeg, ffprobe)
+    }
+
+    /// Checks tools at the given paths.
+    ///
+    /// # Arguments
+    ///
+    /// * `ffmpeg` - The ffmpeg executable.
+    /// * `ffprobe` - The ffprobe executable, if there is one.
+    ///
+    /// # Returns
+    ///
+    /// The checked `Tools`, or `ToolNotFound`/`ToolFailed` if any of the given tools is missing or broken.
+    pub fn from_paths(ffmpeg: PathBuf, ffprobe: Option<PathBuf>) -> Result<Tools, RsframeError> {
+        let ffmpeg_version = version(&ffmpeg)?;
+        if let Some(ffprobe) = &ffprobe {
+            version(ffprobe)?;
+        }
+
+        let output = run(command(&ffmpeg).args(["-hide_banner", "-encoders"]))?;
+        let encoders = parse_encoders(&String::from_utf8_lossy(&output.stdout));
+
+        log::info!(ffmpeg:? = ffmpeg, ffprobe:? = ffprobe, encoders = encoders.len();
+            "using {} ({})", ffmpeg.display(), ffmpeg_version);
+
+        Ok(Tools {
+            ffmpeg,
+            ffprobe,
+            ffmpeg_version,
+            encoders,
+        })
+    }
+
+    /// Returns the process-wide tools. They are detected the first time this is called, unless `set_global` was called first.
+    ///
+    /// # Returns
+    ///
+    /// The process-wide `Tools`, or the error from `Tools::detect`. Detection is tried again on the next call after an error.
+    pub fn global() -> Result<&'static Tools, RsframeError> {
+        if let Some(tools) = GLOBAL.get() {
+            return Ok(tools);
+        }
+
+        let tools = Tools::detect()?;
+        Ok(GLOBAL.get_or_init(|| tools))
+    }
+
+    /// Sets the process-wide tools returned by `global`.
+    ///
+    /// # Returns
+    ///
+    /// The given tools back as an error if the process-wide tools were already set or detected.
+    pub fn set_global(tools: Tools) -> Result<(), Tools> {
+        GLOBAL.set(tools)
+    }
+
+    /// Returns the names of the video encoders ffmpeg was built with, such as `libx264`.
+    pub fn encoders(&self) -> &[String] {
+        &self.encoders
+    }
+
+    /// Returns whether ffmpeg was built with the given encoder.
+    pub fn has_encoder(&self, encoder: &str) -> bool {
+        self.encoders.iter().any(|name| name == encoder)
+    }
+
+    /// Checks that ffmpeg was built with the given encoder.
+    ///
+    /// # Returns
+    ///
+    /// An `UnsupportedEncoder` error if it wasn't.
+    pub fn require_encoder(&self, encoder: &str) -> Result<(), RsframeError> {
+        if self.has_encoder(encoder) {
+            Ok(())
+        } else {
+            Err(RsframeError::UnsupportedEncoder(encoder.to_string()))
+        }
+    }
+
+    /// Returns the ffprobe executable.
+    ///
+    /// # Returns
+    ///
+    /// A `ToolNotFound` error if ffprobe was not found.
+    pub fn ffprobe(&self) -> Result<&Path, RsframeError> {
+        self.ffprobe.as_deref().ok_or_else(|| RsframeError::ToolNotFound { tool: "ffprobe".to_string() })
+    }
+}
+
+/// Private function; finds a tool from its environment variable, or on PATH.
+fn locate(variable: &str, name: &str) -> Option<PathBuf> {
+    from_env(variable).or_else(|| find_on_path(name))
+}
+
+/// Private function; the path an environment variable points a tool at, if it is set.
+fn from_env(variable: &str) -> Option<PathBuf> {
+    match env::var_os(variable) {
+        Some(path) if !path.is_empty() => Some(PathBuf::from(path)),
+        _ => None,
+    }
+}
+
+/// Private function; searches every folder on PATH for an executable.
+fn find_on_path(name: &str) -> Option<PathBuf> {
+    let file_name = if cfg!(windows) { format!("{}.exe", name) } else { name.to_string() };
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|folder| folder.join(&file_name))
+        .find(|path| path.is_file())
+}
+
+/// Private function; runs `<tool> -version` and returns the first line it prints.
+fn version(tool: &Path) -> Result<String, RsframeError> {
+    let output = run(command(tool).arg("-version"))?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string())
+}
+
+/// Private function; reads the video encoders out of `ffmpeg -encoders`.
+/// Each encoder is listed after the `------` line as its capability flags, its name and a description.
+fn parse_encoders(listing: &str) -> Vec<String> {
+    listing
+        .lines()
+        .skip_while(|line| !line.trim_start().starts_with("---"))
+        .skip(1)
+        .filter_map(|line| {
+            let mut columns = line.split_whitespace();
+            let flags = columns.next()?;
+            let name = columns.next()?;
+            if flags.starts_with('V') {
+                Some(name.to_string())
+            } else {
+                None
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,241 @@
+use crate::vfx::resize::Filter;
+use crate::vfx::video::Pixel;
+use rayon::prelude::*;
+
+/// A lossless change of orientation. These only move pixels around, so they are fast and never blur the frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Orientation {
+    Rotate90,       // Turns the frame a quarter turn clockwise
+    Rotate180,      // Turns the frame upside down
+    Rotate270,      // Turns the frame a quarter turn anticlockwise
+    FlipHorizontal, // Mirrors the frame left to right
+    FlipVertical,   // Mirrors the frame top to bottom
+    Transpose,      // Swaps rows and columns, mirroring the frame along the diagonal from its top left corner
+}
+
+impl Orientation {
+    /// Returns the orientation that turns a frame clockwise by the given angle.
+    /// Frames decoded from a file are already upright, so this is not needed for the `rotation` of a `VideoStream`.
+    ///
+    /// # Returns
+    ///
+    /// The orientation, or `None` if the angle is not a multiple of 90 degrees or is a full turn.
+    pub fn from_degrees(degrees: i32) -> Option<Orientation> {
+        match degrees.rem_euclid(360) {
+            90 => Some(Orientation::Rotate90),
+            180 => Some(Orientation::Rotate180),
+            270 => Some(Orientation::Rotate270),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the orientation swaps the width and height of a frame.
+    pub fn swaps_dimensions(self) -> bool {
+        matches!(self, Orientation::Rotate90 | Orientation::Rotate270 | Orientation::Transpose)
+    }
+}
+
+/// An affine transform, which can rotate, scale, skew and move a frame, or any combination of those.
+/// Coordinates are in pixels, with (0, 0) at the top left corner of the frame and y going down, so positive angles turn clockwise.
+/// Transforms are chained in the order they are written: `Affine::rotation(30.0).translate(100.0, 0.0)` rotates and then moves.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Affine {
+    a: f64, // How much x contributes to the new x
+    b: f64, // How much y contributes to the new x
+    c: f64, // How far the new x is moved
+    d: f64, // How much x contributes to the new y
+    e: f64, // How much y contributes to the new y
+    f: f64, // How far the new y is moved
+}
+
+impl Affine {
+    /// Creates a transform that leaves every point where it is.
+    pub fn identity() -> Affine {
+        Affine { a: 1.0, b: 0.0, c: 0.0, d: 0.0, e: 1.0, f: 0.0 }
+    }
+
+    /// Creates a transform that turns points clockwise around (0, 0) by the given angle in degrees.
+    pub fn rotation(degrees: f64) -> Affine {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Affine { a: cos, b: -sin, c: 0.0, d: sin, e: cos, f: 0.0 }
+    }
+
+    /// Creates a transform that scales points away from (0, 0). Negative factors mirror.
+    pub fn scaling(x: f64, y: f64) -> Affine {
+        Affine { a: x, b: 0.0, c: 0.0, d: 0.0, e: y, f: 0.0 }
+    }
+
+    /// Creates a transform that skews points, slanting vertical lines by `x_degrees` and horizontal lines by `y_degrees`.
+    pub fn skewing(x_degrees: f64, y_degrees: f64) -> Affine {
+        Affine { a: 1.0, b: x_degrees.to_radians().tan(), c: 0.0, d: y_degrees.to_radians().tan(), e: 1.0, f: 0.0 }
+    }
+
+    /// Creates a transform that moves points by the given number of pixels.
+    pub fn translation(x: f64, y: f64) -> Affine {
+        Affine { a: 1.0, b: 0.0, c: x, d: 0.0, e: 1.0, f: y }
+    }
+
+    /// Returns a transform that does this one and then `next`.
+    pub fn then(self, next: Affine) -> Affine {
+        Affine {
+            a: next.a * self.a + next.b * self.d,
+            b: next.a * self.b + next.b * self.e,
+            c: next.a * self.c + next.b * self.f + next.c,
+            d: next.d * self.a + next.e * self.d,
+            e: next.d * self.b + next.e * self.e,
+            f: next.d * self.c + next.e * self.f + next.f,
+        }
+    }
+
+    /// Rotates clockwise around (0, 0) after this transform.
+    pub fn rotate(self, degrees: f64) -> Affine {
+        self.then(Affine::rotation(degrees))
+    }
+
+    /// Scales away from (0, 0) after this transform.
+    pub fn scale(self, x: f64, y: f64) -> Affine {
+        self.then(Affine::scaling(x, y))
+    }
+
+    /// Skews after this transform.
+    pub fn skew(self, x_degrees: f64, y_degrees: f64) -> Affine {
+        self.then(Affine::skewing(x_degrees, y_degrees))
+    }
+
+    /// Moves by the given number of pixels after this transform.
+    pub fn translate(self, x: f64, y: f64) -> Affine {
+        self.then(Affine::translation(x, y))
+    }
+
+    /// Returns the same transform, but done around the point (`x`, `y`) instead of (0, 0),
+    /// such as `Affine::rotation(15.0).around(width / 2.0, height / 2.0)` to rotate around the middle of a frame.
+    pub fn around(self, x: f64, y: f64) -> Affine {
+        Affine::translation(-x, -y).then(self).translate(x, y)
+    }
+
+    /// Returns the transform that undoes this one, or `None` if it squashes everything onto a line or a point.
+    pub fn inverse(self) -> Option<Affine> {
+        let determinant = self.a * self.e - self.b * self.d;
+        if determinant.abs() < f64::EPSILON {
+            return None;
+        }
+
+        let (a, b) = (self.e / determinant, -self.b / determinant);
+        let (d, e) = (-self.d / determinant, self.a / determinant);
+        Some(Affine {
+            a,
+            b,
+            c: -(a * self.c + b * self.f),
+            d,
+            e,
+            f: -(d * self.c + e * self.f),
+        })
+    }
+
+    /// Returns where the point (`x`, `y`) ends up.
+    pub fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        (self.a * x + self.b * y + self.c, self.d * x + self.e * y + self.f)
+    }
+}
+
+impl Default for Affine {
+    /// The identity transform.
+    fn default() -> Self {
+        Affine::identity()
+    }
+}
+
+/// Private function; changes the orientation of `pixels`, a `width` by `height` image stored row by row.
+/// The new image is `height` by `width` if the orientation swaps the dimensions.
+pub(crate) fn orient(pixels: &[Pixel], width: usize, height: usize, orientation: Orientation) -> Vec<Pixel> {
+    let new_width = if orientation.swaps_dimensions() { height } else { width };
+    let mut oriented = vec![Pixel::transparent(); pixels.len()];
+    if new_width == 0 {
+        return oriented;
+    }
+
+    oriented.par_chunks_mut(new_width).enumerate().for_each(|(y, row)| {
+        for (x, out) in row.iter_mut().enumerate() {
+            let (source_x, source_y) = match orientation {
+                Orientation::Rotate90 => (y, height - 1 - x),
+                Orientation::Rotate180 => (width - 1 - x, height - 1 - y),
+                Orientation::Rotate270 => (width - 1 - y, x),
+                Orientation::FlipHorizontal => (width - 1 - x, y),
+                Orientation::FlipVertical => (x, height - 1 - y),
+                Orientation::Transpose => (y, x),
+            };
+            *out = pixels[source_y * width + source_x];
+        }
+    });
+    oriented
+}
+
+/// Private function; moves the contents of `pixels`, a `width` by `height` image stored row by row, by `transform`.
+/// Every new pixel is sampled from where the inverse transform puts it, and whatever falls outside the image is `fill`.
+pub(crate) fn warp(pixels: &[Pixel], width: usize, height: usize, transform: &Affine, filter: Filter, fill: Pixel) -> Vec<Pixel> {
+    let mut warped = vec![fill; pixels.len()];
+    let inverse = match transform.inverse() {
+        Some(inverse) if width > 0 => inverse,
+        _ => return warped,
+    };
+
+    let premultiply = |pixel: Pixel| {
+        let alpha = pixel.a as f32 / 255.0;
+        [pixel.r as f32 * alpha, pixel.g as f32 * alpha, pixel.b as f32 * alpha, alpha]
+    };
+    let fill_value = premultiply(fill);
+    let support = filter.support();
+
+    warped.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+        for (x, out) in row.iter_mut().enumerate() {
+            // Sample at the centre of the pixel
+            let (u, v) = inverse.apply(x as f64 + 0.5, y as f64 + 0.5);
+            let (u, v) = (u as f32, v as f32);
+            if u < -support || v < -support || u > width as f32 + support || v > height as f32 + support {
+                continue;
+            }
+
+            if filter == Filter::Nearest {
+                let (sx, sy) = (u.floor(), v.floor());
+                if sx >= 0.0 && sy >= 0.0 && (sx as usize) < width && (sy as usize) < height {
+                    *out = pixels[sy as usize * width + sx as usize];
+                }
+                continue;
+            }
+
+            let mut sum = [0.0; 4];
+            let mut total = 0.0;
+            for sy in (v - 0.5 - support).floor() as i64..=(v - 0.5 + support).ceil() as i64 {
+                let weight_y = filter.weight(sy as f32 + 0.5 - v);
+                if weight_y == 0.0 {
+                    continue;
+                }
+                for sx in (u - 0.5 - support).floor() as i64..=(u - 0.5 + support).ceil() as i64 {
+                    let weight = filter.weight(sx as f32 + 0.5 - u) * weight_y;
+                    if weight == 0.0 {
+                        continue;
+                    }
+
+                    let inside = sx >= 0 && sy >= 0 && (sx as usize) < width && (sy as usize) < height;
+                    let value = if inside { premultiply(pixels[sy as usize * width + sx as usize]) } else { fill_value };
+                    for channel in 0..4 {
+                        sum[channel] += value[channel] * weight;
+                    }
+                    total += weight;
+                }
+            }
+
+            if total == 0.0 {
+                continue;
+            }
+            let alpha = (sum[3] / total).clamp(0.0, 1.0);
+            if alpha <= 0.0 {
+                *out = Pixel::transparent();
+                continue;
+            }
+            let channel = |value: f32| (value / total / alpha).round().clamp(0.0, 255.0) as u8;
+            *out = Pixel::rgba(channel(sum[0]), channel(sum[1]), channel(sum[2]), (alpha * 255.0).round() as u8);
+        }
+    });
+    warped
+}
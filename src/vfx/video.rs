@@ -1,30 +1,43 @@
-use crate::vfx::{compile::build_folder, tmp::{drop_folder, create_tmp_folder}, text::create_text};
+use crate::vfx::{compile::save_source, encode::EncodeOptions, tmp::Workspace, text::{self, Align, Font, TextStyle}, blend::BlendMode, resize::{self, Anchor, Filter, FitMode, ResizeOptions}, transform::{self, Affine, Orientation}, convolve::{self, EdgeMode, Kernel, Region}, color::{self, Adjustment, ColorMatrix, Luma}, lut::{self, Interpolation, Lut3D}, key::{self, ChromaKey, KeySpace}, process::{command, run}, error::RsframeError, tools::Tools, decode::decode_frames, probe::probe, load::LoadOptions, source::FrameSource, progress::Monitor};
+use std::borrow::Cow;
+use std::fmt;
 use image::{Rgb, RgbImage};
-use std::process::Command;
 use rayon::prelude::*;
 use std::fs;
-use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// A single pixel, typically used for representing a colour.
-/// Pixels do not support alpha layers, so you should use `chroma_key` functions instead.
-#[derive(Clone, Copy, Debug)]
+/// Pixels have an alpha layer, from 0 (fully transparent) to 255 (fully opaque), which `draw_over` uses for blending.
+/// The alpha is not premultiplied, so a half transparent red is `Pixel::rgba(255, 0, 0, 128)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Pixel {
     pub r: u8, // Red component of the pixel
     pub g: u8, // Green component of the pixel
     pub b: u8, // Blue component of the pixel
+    pub a: u8, // Alpha component of the pixel, where 0 is transparent
 }
 
 impl Pixel {
-    /// Creates a new pixel with the colours.
+    /// Creates a new, fully opaque pixel with the colours.
     pub fn new(r: u8, g: u8, b: u8) -> Pixel {
+        Pixel::rgba(r, g, b, 255)
+    }
+
+    /// Creates a new pixel with the colours and alpha.
+    pub fn rgba(r: u8, g: u8, b: u8, a: u8) -> Pixel {
         Pixel {
             r,
             g,
-            b
+            b,
+            a
         }
     }
 
+    /// Creates a fully transparent pixel
+    pub fn transparent() -> Pixel {
+        Pixel::rgba(0, 0, 0, 0)
+    }
+
     /// Creates a black pixel
     pub fn black() -> Pixel {
         Pixel::new(0, 0, 0)
@@ -49,6 +62,52 @@ impl Pixel {
     pub fn blue() -> Pixel {
         Pixel::new(0, 0, 255)
     }
+
+    /// Returns whether the pixel is fully opaque.
+    pub fn is_opaque(&self) -> bool {
+        self.a == 255
+    }
+
+    /// Composites this pixel over another with the Porter-Duff "over" operator, the way layers are stacked in an image editor.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `under` - The pixel underneath this one.
+    /// * `opacity` - How much of this pixel to use, from 0 (none) to 1 (all of its alpha). It is multiplied with the pixel's own alpha.
+    /// 
+    /// # Returns
+    /// 
+    /// The blended pixel. It is opaque whenever `under` is.
+    pub fn over(self, under: Pixel, opacity: f32) -> Pixel {
+        let top = self.a as f32 / 255.0 * opacity.clamp(0.0, 1.0);
+        if top >= 1.0 {
+            return self;
+        }
+        if top <= 0.0 {
+            return under;
+        }
+
+        // Straight alpha: colours are weighted by their own alpha, then divided by the result's
+        let bottom = under.a as f32 / 255.0 * (1.0 - top);
+        let alpha = top + bottom;
+        let mix = |over: u8, under: u8| ((over as f32 * top + under as f32 * bottom) / alpha).round() as u8;
+
+        Pixel {
+            r: mix(self.r, under.r),
+            g: mix(self.g, under.g),
+            b: mix(self.b, under.b),
+            a: (alpha * 255.0).round() as u8,
+        }
+    }
+
+    /// Private function; returns the colour as it looks over black, which is how transparent pixels end up in videos and images.
+    fn flatten(self) -> [u8; 3] {
+        if self.is_opaque() {
+            return [self.r, self.g, self.b];
+        }
+        let scale = |c: u8| ((c as u16 * self.a as u16 + 127) / 255) as u8;
+        [scale(self.r), scale(self.g), scale(self.b)]
+    }
 }
 
 /// The VideoPosition enum is used for determining where to put a transition effect in the video.
@@ -58,6 +117,94 @@ pub enum VideoPosition {
     START, // Indicates the transition effect should be applied at the start of the video
 }
 
+/// A frame rate, stored as a fraction so that rates like 29.97 (`30000/1001`) are exact.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameRate {
+    pub numerator: u32,   // Frames per `denominator` seconds
+    pub denominator: u32, // The number of seconds `numerator` frames take
+}
+
+impl FrameRate {
+    /// Creates a frame rate of `numerator / denominator` frames per second.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `numerator` - The number of frames.
+    /// * `denominator` - The number of seconds those frames take. A denominator of zero is treated as one.
+    /// 
+    /// # Returns
+    /// 
+    /// A new `FrameRate` instance, reduced to its lowest terms.
+    pub fn new(numerator: u32, denominator: u32) -> FrameRate {
+        let denominator = denominator.max(1);
+        let divisor = gcd(numerator, denominator).max(1);
+
+        FrameRate {
+            numerator: numerator / divisor,
+            denominator: denominator / divisor,
+        }
+    }
+
+    /// Creates a whole-number frame rate, such as 24, 30 or 120.
+    pub fn from_fps(fps: u32) -> FrameRate {
+        FrameRate::new(fps, 1)
+    }
+
+    /// Parses a frame rate the way FFmpeg writes them, such as `30000/1001`, `25` or `29.97`.
+    /// 
+    /// # Returns
+    /// 
+    /// The parsed `FrameRate`, or `None` if the text is not a valid, non-zero frame rate.
+    pub fn parse(text: &str) -> Option<FrameRate> {
+        let text = text.trim();
+        let rate = if let Some((numerator, denominator)) = text.split_once('/') {
+            FrameRate::new(numerator.trim().parse().ok()?, denominator.trim().parse().ok()?)
+        } else if let Ok(fps) = text.parse::<u32>() {
+            FrameRate::from_fps(fps)
+        } else {
+            // Decimal rates are kept to a thousandth of a frame
+            let fps = text.parse::<f64>().ok()?;
+            if !fps.is_finite() || fps <= 0.0 {
+                return None;
+            }
+            FrameRate::new((fps * 1000.0).round() as u32, 1000)
+        };
+
+        if rate.numerator == 0 {
+            None
+        } else {
+            Some(rate)
+        }
+    }
+
+    /// Returns the frame rate in frames per second.
+    pub fn as_f64(&self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+}
+
+impl Default for FrameRate {
+    /// 24 frames per second.
+    fn default() -> Self {
+        FrameRate::from_fps(24)
+    }
+}
+
+impl fmt::Display for FrameRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.numerator, self.denominator)
+    }
+}
+
+/// Private function; the greatest common divisor of two numbers, used for reducing frame rates.
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
 /// Private function; used for interpolating colours in the `tint` function.
 /// 
 /// # Arguments
@@ -73,7 +220,18 @@ fn lerp(t: f32, start: f32, end: f32) -> f32 {
     start + (end - start) * t
 }
 
-/// A frame is a single frame in a video, it can be represented as an RGB image.
+/// Private function; the keyer `draw_with_chroma_key` uses, which removes every pixel whose red, green and blue are all
+/// within `tolerance` of the key colour and leaves the rest as they are.
+fn exact_key(color: Pixel, tolerance: u8) -> ChromaKey {
+    let tolerance = tolerance as f32 / 255.0;
+    ChromaKey::new(color)
+        .space(KeySpace::Rgb)
+        .tolerance(tolerance, tolerance)
+        .despill(0.0)
+}
+
+/// A frame is a single frame in a video, it can be represented as an RGBA image.
+/// Videos have no alpha layer, so transparent pixels are saved as if they were over black.
 #[derive(Clone)]
 pub struct Frame {
     pixels: Vec<Pixel>, // The pixel data of the frame
@@ -98,7 +256,7 @@ impl Frame {
 
         for _x in 0..width {
             for _y in 0..height {
-                pixels.push(background.clone());
+                pixels.push(background);
             }
         }
 
@@ -109,34 +267,46 @@ impl Frame {
         }
     }
 
-    /// Returns a Frame of the text given. Requires ImageMagick installed and added to PATH.
+    /// Returns a Frame of the text given, centred on a transparent background. Each line of the text is centred on its own.
+    /// The text is anti-aliased into the alpha layer, so it can be drawn over other frames with `draw_over` without jagged edges.
+    /// Saved as it is, the background is black.
     /// 
     /// # Arguments
     /// 
     /// * `width` - The width of the frame.
     /// * `height` - The height of the frame.
-    /// * `font` - The font to be used for the text.
+    /// * `font` - The font to be used for the text, loaded with `Font::from_file` or `Font::from_bytes`.
+    /// * `size` - The height of the font, in pixels.
     /// * `color` - The colour of the text.
     /// * `text` - The text to be rendered.
     /// 
     /// # Returns
     /// 
     /// A new `Frame` instance containing the rendered text.
-    pub fn text(width: usize, height: usize, font: String, color: String, text: String, magick: &str) -> Result<Frame, String> {
-        let path = create_text(text, font, color, width, height, magick);
-
-        let frame = Frame::from_img(path.clone());
-
-        let rm_results = fs::remove_file(path);
-        match rm_results {
-            Ok(_) => {},
-            Err(_) => eprintln!("Could not delete text image.")
-        }
+    pub fn text(width: usize, height: usize, font: &Font, size: f32, color: Pixel, text: &str) -> Frame {
+        let mut frame = Frame::new(width, height, Pixel::transparent());
+        let style = TextStyle::new(font.clone(), size).color(color).align(Align::Center);
+
+        let (text_width, text_height) = style.measure(text);
+        let x = (width as f32 - text_width as f32) / 2.0;
+        let y = (height as f32 - text_height as f32) / 2.0;
+        text::draw(&mut frame, text, x, y, &style);
+        frame
+    }
 
-        Ok(frame?)
+    /// Draws text onto the frame, laid out and decorated by `style`. Use `TextStyle::measure` to find out how much room it takes.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `text` - The text to draw. Each `\n` starts a new paragraph.
+    /// * `x` - The x-coordinate of the top left corner of the text, or of its background box if it has one.
+    /// * `y` - The y-coordinate of the top left corner of the text, or of its background box if it has one.
+    /// * `style` - The font, size, colour, wrapping, alignment and decoration of the text.
+    pub fn draw_text(&mut self, text: &str, x: usize, y: usize, style: &TextStyle) {
+        text::draw(self, text, x as f32, y as f32, style);
     }
 
-    /// Returns a Frame that is an identical copy of the image provided.
+    /// Returns a Frame that is an identical copy of the image provided. The alpha layer of images that have one, such as PNGs, is kept.
     /// 
     /// # Arguments
     /// 
@@ -144,22 +314,15 @@ impl Frame {
     /// 
     /// # Returns
     /// 
-    /// A `Result` containing the new `Frame` or an error message.
-    pub fn from_img(image_path: String) -> Result<Frame, String> {
-        let img = match image::open(image_path) {
-            Ok(img) => img.to_rgb8(),
-            Err(err) => return Err(format!("Failed to open image: {}", err)),
-        };
+    /// A `Result` containing the new `Frame` or the error that stopped the image from loading.
+    pub fn from_img(image_path: String) -> Result<Frame, RsframeError> {
+        let img = image::open(image_path)?.to_rgba8();
         let mut pixels = Vec::with_capacity((img.width() * img.height()) as usize);
 
         for y in 0..img.height() {
             for x in 0..img.width() {
                 let pixel_value = img.get_pixel(x, y).0;
-                pixels.push(Pixel {
-                    r: pixel_value[0],
-                    g: pixel_value[1],
-                    b: pixel_value[2],
-                });
+                pixels.push(Pixel::rgba(pixel_value[0], pixel_value[1], pixel_value[2], pixel_value[3]));
             }
         }
 
@@ -170,6 +333,29 @@ impl Frame {
         })
     }
 
+    /// Private function; creates a frame from tightly packed 8-bit RGB data, row by row.
+    pub(crate) fn from_rgb(width: usize, height: usize, bytes: &[u8]) -> Frame {
+        let pixels = bytes
+            .chunks_exact(3)
+            .map(|rgb| Pixel::new(rgb[0], rgb[1], rgb[2]))
+            .collect();
+
+        Frame {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Private function; returns the frame as tightly packed 8-bit RGB data, row by row, with transparent pixels over black.
+    pub(crate) fn to_rgb(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.pixels.len() * 3);
+        for pixel in &self.pixels {
+            bytes.extend_from_slice(&pixel.flatten());
+        }
+        bytes
+    }
+
     /// Replaces the pixel at the given coordinates with the given colour.
     /// 
     /// # Arguments
@@ -196,6 +382,7 @@ impl Frame {
     }
 
     /// Replaces the current frame with a new image. The new image is the resolution given with a background colour of the colour given. The image is put in the centre of the new image.
+    /// If the new resolution is smaller than the frame, the edges of the frame are cropped off. This is `fit` with `FitMode::Pad` and `Anchor::CENTER`.
     /// 
     /// # Arguments
     /// 
@@ -203,18 +390,287 @@ impl Frame {
     /// * `target_height` - The height of the new frame.
     /// * `background` - The background colour of the new frame.
     pub fn expand(&mut self, target_width: usize, target_height: usize, background: Pixel) {
+        self.fit(target_width, target_height, FitMode::Pad, Anchor::CENTER, background);
+    }
+
+    /// Makes the frame fill a new resolution, by scaling it and then padding or cropping it, such as turning 16:9 footage into 9:16.
+    /// Frames are scaled with `Filter::Lanczos3`; use `fit_with` to pick another filter.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `target_width` - The width of the new frame.
+    /// * `target_height` - The height of the new frame.
+    /// * `mode` - Whether to fit the frame inside (`Contain`), fill and crop (`Cover`), `Stretch` it or only `Pad` it.
+    /// * `anchor` - Which part of the frame stays in view, such as `Anchor::CENTER` or `Anchor::TOP`.
+    /// * `background` - The colour of the padding.
+    pub fn fit(&mut self, target_width: usize, target_height: usize, mode: FitMode, anchor: Anchor, background: Pixel) {
+        self.fit_with(target_width, target_height, mode, anchor, background, &ResizeOptions::new());
+    }
+
+    /// Does the same thing as `fit`, but scales the frame with the given options.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `target_width` - The width of the new frame.
+    /// * `target_height` - The height of the new frame.
+    /// * `mode` - Whether to fit the frame inside (`Contain`), fill and crop (`Cover`), `Stretch` it or only `Pad` it.
+    /// * `anchor` - Which part of the frame stays in view, such as `Anchor::CENTER` or `Anchor::TOP`.
+    /// * `background` - The colour of the padding.
+    /// * `options` - The filter and colour handling to scale with.
+    pub fn fit_with(&mut self, target_width: usize, target_height: usize, mode: FitMode, anchor: Anchor, background: Pixel, options: &ResizeOptions) {
+        *self = self.fitted(target_width, target_height, mode, anchor, background, options);
+    }
+
+    /// Private function; returns a copy of the frame fitted to the new resolution, as `fit_with` describes.
+    fn fitted(&self, target_width: usize, target_height: usize, mode: FitMode, anchor: Anchor, background: Pixel, options: &ResizeOptions) -> Frame {
+        let (width, height) = resize::fitted_size(self.width, self.height, target_width, target_height, mode);
+        let scaled = if (width, height) == (self.width, self.height) {
+            Cow::Borrowed(&self.pixels)
+        } else {
+            Cow::Owned(resize::resample(&self.pixels, self.width, self.height, width, height, options))
+        };
+
         let mut new = Frame::new(target_width, target_height, background);
-        let (x, y) = (target_width/2-(self.width/2), target_height/2-(self.height/2));
+        let (x_offset, y_offset) = anchor.offset(width, height, target_width, target_height);
+
+        // Only the part of each row that lands on the new frame is copied
+        let x_start = (-x_offset).clamp(0, width as i64) as usize;
+        let x_end = (target_width as i64 - x_offset).clamp(0, width as i64) as usize;
+        if x_start < x_end {
+            for y in 0..height {
+                let target_y = y as i64 + y_offset;
+                if target_y < 0 || target_y >= target_height as i64 {
+                    continue;
+                }
 
-        for x2 in 0..self.width {
-            for y2 in 0..self.height {
-                new.put_pixel(x2+x, y2+y, self.get_pixel(x2,y2));
+                let target = target_y as usize * target_width + (x_start as i64 + x_offset) as usize;
+                new.pixels[target..target + x_end - x_start].copy_from_slice(&scaled[y * width + x_start..y * width + x_end]);
             }
         }
+        new
+    }
+
+    /// Scales the frame to a new resolution with the given filter. The aspect ratio is not kept.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `width` - The new width of the frame.
+    /// * `height` - The new height of the frame.
+    /// * `filter` - The filter to scale with, such as `Filter::Lanczos3`.
+    pub fn resize(&mut self, width: usize, height: usize, filter: Filter) {
+        self.resize_with(width, height, &ResizeOptions::new().filter(filter));
+    }
+
+    /// Scales the frame to a new resolution, with options such as gamma-correct filtering. The rows of the frame are scaled in parallel.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `width` - The new width of the frame.
+    /// * `height` - The new height of the frame.
+    /// * `options` - The filter and colour handling to scale with.
+    pub fn resize_with(&mut self, width: usize, height: usize, options: &ResizeOptions) {
+        if width == self.width && height == self.height {
+            return;
+        }
+
+        self.pixels = resize::resample(&self.pixels, self.width, self.height, width, height, options);
+        self.width = width;
+        self.height = height;
+    }
+
+    /// Changes the orientation of the frame, such as turning it a quarter turn. This only moves pixels around, so nothing is blurred.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `orientation` - The rotation or flip to do.
+    pub fn orient(&mut self, orientation: Orientation) {
+        self.pixels = transform::orient(&self.pixels, self.width, self.height, orientation);
+        if orientation.swaps_dimensions() {
+            std::mem::swap(&mut self.width, &mut self.height);
+        }
+    }
+
+    /// Turns the frame a quarter turn clockwise.
+    pub fn rotate90(&mut self) {
+        self.orient(Orientation::Rotate90);
+    }
+
+    /// Turns the frame upside down.
+    pub fn rotate180(&mut self) {
+        self.orient(Orientation::Rotate180);
+    }
+
+    /// Turns the frame a quarter turn anticlockwise.
+    pub fn rotate270(&mut self) {
+        self.orient(Orientation::Rotate270);
+    }
 
-        self.width = new.width;
-        self.height = new.height;
-        self.pixels = new.pixels;
+    /// Mirrors the frame left to right.
+    pub fn flip_horizontal(&mut self) {
+        self.orient(Orientation::FlipHorizontal);
+    }
+
+    /// Mirrors the frame top to bottom.
+    pub fn flip_vertical(&mut self) {
+        self.orient(Orientation::FlipVertical);
+    }
+
+    /// Swaps the rows and columns of the frame.
+    pub fn transpose(&mut self) {
+        self.orient(Orientation::Transpose);
+    }
+
+    /// Rotates, scales, skews or moves the contents of the frame. The frame keeps its size, so whatever is moved out of it is lost.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `affine` - The transform to do, in pixels from the top left corner of the frame.
+    /// * `filter` - The filter to sample the frame with, such as `Filter::Bilinear` or `Filter::Bicubic`.
+    /// * `fill` - The colour of the areas nothing is moved into, such as `Pixel::transparent()`.
+    pub fn transform(&mut self, affine: &Affine, filter: Filter, fill: Pixel) {
+        self.pixels = transform::warp(&self.pixels, self.width, self.height, affine, filter, fill);
+    }
+
+    /// Rotates the contents of the frame clockwise around its middle by any angle. The frame keeps its size, so the corners are cut off.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `degrees` - The angle to rotate by.
+    /// * `filter` - The filter to sample the frame with, such as `Filter::Bilinear` or `Filter::Bicubic`.
+    /// * `fill` - The colour of the areas nothing is rotated into, such as `Pixel::transparent()`.
+    pub fn rotate(&mut self, degrees: f64, filter: Filter, fill: Pixel) {
+        let affine = Affine::rotation(degrees).around(self.width as f64 / 2.0, self.height as f64 / 2.0);
+        self.transform(&affine, filter, fill);
+    }
+
+    /// Replaces every pixel with the weighted sum of its neighbours, using the weights of the kernel.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `kernel` - The weights, such as `Kernel::gaussian(2.0)` or one made with `Kernel::new`.
+    /// * `edge` - How pixels past the edges of the frame are read.
+    pub fn convolve(&mut self, kernel: &Kernel, edge: EdgeMode) {
+        self.convolve_region(kernel, edge, self.region());
+    }
+
+    /// Does the same thing as `convolve`, but only changes the pixels inside `region`. Pixels around the region are still read.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `kernel` - The weights, such as `Kernel::gaussian(2.0)` or one made with `Kernel::new`.
+    /// * `edge` - How pixels past the edges of the frame are read.
+    /// * `region` - The part of the frame to change.
+    pub fn convolve_region(&mut self, kernel: &Kernel, edge: EdgeMode, region: Region) {
+        convolve::convolve(&mut self.pixels, self.width, self.height, kernel, edge, region);
+    }
+
+    /// Blurs the frame with a Gaussian blur.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `radius` - The strength of the blur, in pixels. This is the standard deviation, like the CSS `blur()` filter.
+    pub fn gaussian_blur(&mut self, radius: f32) {
+        self.gaussian_blur_region(radius, self.region());
+    }
+
+    /// Blurs part of the frame with a Gaussian blur, such as a licence plate or a face.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `radius` - The strength of the blur, in pixels. This is the standard deviation, like the CSS `blur()` filter.
+    /// * `region` - The part of the frame to blur.
+    pub fn gaussian_blur_region(&mut self, radius: f32, region: Region) {
+        self.convolve_region(&Kernel::gaussian(radius), EdgeMode::Clamp, region);
+    }
+
+    /// Blurs the frame by averaging the square around every pixel. This is faster than a Gaussian blur, but blockier.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `radius` - How far the square reaches from every pixel, in pixels.
+    pub fn box_blur(&mut self, radius: usize) {
+        self.box_blur_region(radius, self.region());
+    }
+
+    /// Blurs part of the frame by averaging the square around every pixel.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `radius` - How far the square reaches from every pixel, in pixels.
+    /// * `region` - The part of the frame to blur.
+    pub fn box_blur_region(&mut self, radius: usize, region: Region) {
+        self.convolve_region(&Kernel::box_blur(radius), EdgeMode::Clamp, region);
+    }
+
+    /// Sharpens the frame with a 3x3 kernel. Use `unsharp_mask` for more control.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `amount` - How much to sharpen, where 1 is a standard sharpen and 0 changes nothing.
+    pub fn sharpen(&mut self, amount: f32) {
+        self.convolve(&Kernel::sharpen(amount), EdgeMode::Clamp);
+    }
+
+    /// Sharpens the frame with an unsharp mask, which boosts the difference between the frame and a blurred copy of it.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `radius` - The radius of the blur, in pixels. Larger radii sharpen larger details.
+    /// * `amount` - How much of the difference to add, such as 0.5 to 1.5.
+    /// * `threshold` - Differences up to this, from 0 to 255, are left alone so noise and smooth areas aren't sharpened.
+    pub fn unsharp_mask(&mut self, radius: f32, amount: f32, threshold: u8) {
+        self.unsharp_mask_region(radius, amount, threshold, self.region());
+    }
+
+    /// Sharpens part of the frame with an unsharp mask.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `radius` - The radius of the blur, in pixels. Larger radii sharpen larger details.
+    /// * `amount` - How much of the difference to add, such as 0.5 to 1.5.
+    /// * `threshold` - Differences up to this, from 0 to 255, are left alone so noise and smooth areas aren't sharpened.
+    /// * `region` - The part of the frame to sharpen.
+    pub fn unsharp_mask_region(&mut self, radius: f32, amount: f32, threshold: u8, region: Region) {
+        convolve::unsharp_mask(&mut self.pixels, self.width, self.height, radius, amount, threshold, region);
+    }
+
+    /// Embosses the frame, which makes its edges look raised.
+    pub fn emboss(&mut self) {
+        self.convolve(&Kernel::emboss(), EdgeMode::Clamp);
+    }
+
+    /// Private function; the region covering the whole frame.
+    fn region(&self) -> Region {
+        Region::new(0, 0, self.width, self.height)
+    }
+
+    /// Colour corrects the frame, applying each adjustment in turn. The alpha layer is left as it is.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `adjustments` - The adjustments to apply, such as `[Adjustment::Contrast(0.2), Adjustment::Saturation(-0.3)]`.
+    pub fn adjust(&mut self, adjustments: &[Adjustment]) {
+        color::adjust(&mut self.pixels, adjustments);
+    }
+
+    /// Grades the frame with a LUT, such as a look loaded with `Lut3D::from_cube_file`. The alpha layer is left as it is.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `lut` - The LUT to look colours up in.
+    /// * `interpolation` - How colours between the entries of the LUT are worked out, such as `Interpolation::Tetrahedral`.
+    /// * `intensity` - How much of the look to use, from 0 (none) to 1 (all of it).
+    pub fn apply_lut(&mut self, lut: &Lut3D, interpolation: Interpolation, intensity: f32) {
+        lut::apply(&mut self.pixels, lut, interpolation, intensity);
+    }
+
+    /// Recolours the frame with a colour matrix, such as `ColorMatrix::sepia()` or `ColorMatrix::duotone(..)`.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `matrix` - The matrix that works out the new channels of every pixel from its old ones.
+    pub fn apply_matrix(&mut self, matrix: &ColorMatrix) {
+        color::apply_matrix(&mut self.pixels, matrix);
     }
 
     /// Tints the image with the Pixel colour provided and strength provided. Strength is 0-1, where one fully replaces the image with the colour and 0 keeps it the same.
@@ -231,25 +687,31 @@ impl Frame {
         }
     }
 
-    /// Turns the frame monochrome and removes all colour.
+    /// Turns the frame monochrome and removes all colour, weighting the channels by how bright they look in HD video.
+    /// Use `greyscale` to pick other weights.
     pub fn monochrome(&mut self) {
-        for pixel in &mut self.pixels {
-            let avg = ((pixel.r as usize + pixel.g as usize + pixel.b as usize) / 3) as u8;
+        self.greyscale(Luma::Rec709);
+    }
 
-            pixel.r = avg;
-            pixel.g = avg;
-            pixel.b = avg;
-        }
+    /// Turns the frame into greys of the same brightness. The alpha layer is left as it is.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `luma` - How the brightness of each pixel is worked out, such as `Luma::Rec709`.
+    pub fn greyscale(&mut self, luma: Luma) {
+        color::apply_matrix(&mut self.pixels, &ColorMatrix::greyscale(luma));
     }
 
-    /// Layers another Frame on top of the current frame.
+    /// Layers another Frame on top of the current frame, blending it in by its alpha layer and the blend mode.
     /// 
     /// # Arguments
     /// 
     /// * `other` - The frame to be drawn over the current frame.
     /// * `x_offset` - The x-coordinate offset for the overlay.
     /// * `y_offset` - The y-coordinate offset for the overlay.
-    pub fn draw_over(&mut self, other: &Frame, x_offset: usize, y_offset: usize) {
+    /// * `opacity` - The opacity of the whole overlay, from 0 (invisible) to 1 (as opaque as its pixels).
+    /// * `mode` - How the colours of the overlay combine with the frame, such as `BlendMode::Normal` or `BlendMode::Multiply`.
+    pub fn draw_over(&mut self, other: &Frame, x_offset: usize, y_offset: usize, opacity: f32, mode: BlendMode) {
         for y in 0..other.height {
             for x in 0..other.width {
                 // Calculate the target position on the current frame
@@ -258,14 +720,15 @@ impl Frame {
 
                 // Ensure we don't go out of bounds
                 if target_x < self.width && target_y < self.height {
-                    let other_pixel = other.get_pixel(x, y);
-                    self.put_pixel(target_x, target_y, other_pixel);
+                    let under = self.get_pixel(target_x, target_y);
+                    self.put_pixel(target_x, target_y, mode.composite(other.get_pixel(x, y), under, opacity));
                 }
             }
         }
     }
 
     /// Does the same thing as `draw_over` but also removes the background color provided to add transparency.
+    /// Pixels are either kept or removed, so for green-screen footage use `draw_keyed`, which gives soft edges and removes spill.
     /// 
     /// # Arguments
     /// 
@@ -273,35 +736,54 @@ impl Frame {
     /// * `x_offset` - The x-coordinate offset for the overlay.
     /// * `y_offset` - The y-coordinate offset for the overlay.
     /// * `chroma_key` - The colour to be treated as transparent.
-    /// * `tolerance` - The tolerance level for the chroma keying effect.
+    /// * `tolerance` - How far each of red, green and blue may be from the key colour for a pixel to be removed.
     pub fn draw_with_chroma_key(
         &mut self,
         other: &Frame,
         x_offset: usize,
         y_offset: usize,
         chroma_key: Pixel,
-        tolerance: u8, // New: Add a tolerance level
+        tolerance: u8,
     ) {
-        for y in 0..other.height {
-            for x in 0..other.width {
-                let target_x = x + x_offset;
-                let target_y = y + y_offset;
-
-                if target_x < self.width && target_y < self.height {
-                    let other_pixel = other.get_pixel(x, y);
+        self.draw_keyed(other, x_offset, y_offset, &exact_key(chroma_key, tolerance));
+    }
 
-                    // Check if the pixel is close to the chroma key (with tolerance)
-                    let is_chroma_key = (chroma_key.r as i16 - other_pixel.r as i16).abs() <= tolerance as i16
-                        && (chroma_key.g as i16 - other_pixel.g as i16).abs() <= tolerance as i16
-                        && (chroma_key.b as i16 - other_pixel.b as i16).abs() <= tolerance as i16;
+    /// Removes a green or blue screen from the frame, making it transparent with soft edges and taking its colour out of the foreground.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `key` - The keyer, such as `ChromaKey::new(Pixel::new(70, 180, 80))`.
+    pub fn chroma_key(&mut self, key: &ChromaKey) {
+        key::key(&mut self.pixels, key);
+    }
 
-                    if !is_chroma_key {
-                        self.put_pixel(target_x, target_y, other_pixel);
-                    }
-                }
-            }
+    /// Returns the matte a keyer makes of the frame, for checking and tuning it: white where the frame is kept,
+    /// black where it is removed and grey where it is partly transparent.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `key` - The keyer to make the matte with.
+    pub fn matte(&self, key: &ChromaKey) -> Frame {
+        Frame {
+            pixels: key::matte(&self.pixels, key),
+            width: self.width,
+            height: self.height,
         }
     }
+
+    /// Keys another Frame with a keyer and layers it on top of the current frame, such as green-screen footage over a background.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `other` - The frame to be keyed and drawn over the current frame.
+    /// * `x_offset` - The x-coordinate offset for the overlay.
+    /// * `y_offset` - The y-coordinate offset for the overlay.
+    /// * `key` - The keyer that removes the screen from `other`.
+    pub fn draw_keyed(&mut self, other: &Frame, x_offset: usize, y_offset: usize, key: &ChromaKey) {
+        let mut keyed = other.clone();
+        keyed.chroma_key(key);
+        self.draw_over(&keyed, x_offset, y_offset, 1.0, BlendMode::Normal);
+    }
 }
 
 /// Takes a video file and an audio file and combines them together.
@@ -311,12 +793,13 @@ impl Frame {
 /// * `input_video` - The path to the input video file.
 /// * `input_audio` - The path to the input audio file.
 /// * `output_path` - The path where the output file will be saved.
+/// * `tools` - The tools to combine with, such as `Tools::global()`.
 /// 
 /// # Returns
 /// 
 /// A `Result` indicating success or failure of the operation.
-pub fn combine_video_and_audio(input_video: &str, input_audio: &str, output_path: &str, ffmpeg: &str) -> Result<(), String> {
-    let output = Command::new(ffmpeg)
+pub fn combine_video_and_audio(input_video: &str, input_audio: &str, output_path: &str, tools: &Tools) -> Result<(), RsframeError> {
+    run(command(&tools.ffmpeg)
         .arg("-i")
         .arg(input_video)
         .arg("-i")
@@ -329,26 +812,24 @@ pub fn combine_video_and_audio(input_video: &str, input_audio: &str, output_path
         .arg("0:v")
         .arg("-map")
         .arg("1:a")
-        .arg(output_path)
-        .output();
+        .arg(output_path))?;
 
-    match output {
-        Ok(output) if output.status.success() => Ok(()),
-        Ok(output) => Err(String::from_utf8_lossy(&output.stderr).to_string()),
-        Err(err) => Err(err.to_string()),
-    }
+    Ok(())
 }
 
-/// The main class for handling videos. A video is a list of frames, with a set width and height for consistency.
+/// The main class for handling videos. A video is a list of frames, with a set width, height and frame rate for consistency.
 #[derive(Clone)]
 pub struct Video {
-    frames: Vec<Frame>, // The frames that make up the video
-    pub width: usize,   // The width of the video
-    pub height: usize   // The height of the video
+    frames: Vec<Frame>,         // The frames that make up the video
+    pub width: usize,           // The width of the video
+    pub height: usize,          // The height of the video
+    pub frame_rate: FrameRate,  // The frame rate the video is played and saved at
+    monitor: Monitor            // Receives the progress of bulk effects and can cancel them
 }
 
 impl Video {
-    /// Creates a new Video instance with the specified width and height.
+    /// Creates a new Video instance with the specified width and height, at 24 frames per second.
+    /// Set `frame_rate` to use a different frame rate.
     /// 
     /// # Arguments
     /// 
@@ -362,93 +843,172 @@ impl Video {
         Video {
             width,
             height,
-            frames: Vec::new()
+            frame_rate: FrameRate::default(),
+            frames: Vec::new(),
+            monitor: Monitor::default()
         }
     }
 
-    /// Creates a Video from a file, extracting frames using FFmpeg.
+    /// Creates a Video from a file, extracting frames using FFmpeg. The video keeps the frame rate of the file.
+    /// The file is checked with ffprobe first, and the frames are read straight from FFmpeg's output, so no temporary files are written.
+    /// If ffprobe is not installed next to FFmpeg, the frames are extracted as PNG images instead
+    /// and the frame rate is left at 24 frames per second.
     /// 
     /// # Arguments
     /// 
     /// * `filename` - The path to the video file.
+    /// * `tools` - The tools to decode with, such as `Tools::global()`.
     /// 
     /// # Returns
     /// 
-    /// A `Result` containing the new `Video` or an error message.
-    pub fn from_file(filename: String, ffmpeg: &str) -> Result<Video, String> {
-        let temp = create_tmp_folder();
-
-        let output = Command::new(ffmpeg)
-            .arg("-i")
-            .arg(filename.as_str())
-            .arg(format!("{}/image%d.png", temp))
-            .status();
+    /// A `Result` containing the new `Video` or the error that stopped it from loading.
+    /// Files without a video stream return an `InvalidMedia` error.
+    pub fn from_file(filename: String, tools: &Tools) -> Result<Video, RsframeError> {
+        Video::from_file_with(filename, &LoadOptions::default(), tools)
+    }
 
-        if let Err(err) = output {
-            return Err(format!("FFmpeg command failed: {}", err));
+    /// Creates a Video from part of a file, such as a time or frame range, every Nth frame or at most a number of frames.
+    /// Seeking happens in FFmpeg, so the frames before the start are never decoded.
+    /// When frames are skipped with a stride, the frame rate of the video is lowered to match.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `filename` - The path to the video file.
+    /// * `options` - Which frames of the file to decode.
+    /// * `tools` - The tools to decode with, such as `Tools::global()`.
+    /// 
+    /// # Returns
+    /// 
+    /// A `Result` containing the new `Video` or the error that stopped it from loading.
+    /// An empty range returns an `InvalidRange` error.
+    pub fn from_file_with(filename: String, options: &LoadOptions, tools: &Tools) -> Result<Video, RsframeError> {
+        if tools.ffprobe.is_none() {
+            log::warn!(file = filename.as_str(); "ffprobe was not found, decoding {} through temporary images", filename);
+            return Video::from_images(filename, options, &tools.ffmpeg);
         }
+        let info = probe(&filename, tools)?;
 
-        if !output.unwrap().success() {
-            return Err("FFmpeg did not exit successfully.".to_string());
+        let stream = info.video()
+            .ok_or_else(|| RsframeError::InvalidMedia(format!("{} has no video stream", filename)))?;
+        let (width, height) = stream.display_size();
+        if width == 0 || height == 0 {
+            return Err(RsframeError::InvalidMedia(format!("{} has a video stream without a size", filename)));
         }
 
-        let dir_path = Path::new(&temp);
-        let entries: Vec<_> = match fs::read_dir(dir_path) {
-            Ok(entries) => entries
-                .filter_map(|entry| entry.ok())
-                .map(|entry| entry.path())
-                .filter(|path| path.is_file())
-                .collect(),
-            Err(err) => {
-                drop_folder(temp);
-                return Err(format!("Failed to read temporary directory: {}", err));
-            }
+        let frame_rate = stream.frame_rate.unwrap_or_default();
+        options.validate(frame_rate)?;
+
+        let capacity = match (options.frame_limit(frame_rate), stream.estimated_frames()) {
+            (Some(limit), Some(estimate)) => limit.min(estimate),
+            (limit, estimate) => limit.or(estimate).unwrap_or(0),
         };
 
-        let mut sorted_entries = entries.clone();
-        sorted_entries.sort_by(|a, b| {
-            // Extract frame numbers from filenames and compare numerically
-            let a_num = a.file_name().unwrap().to_str().unwrap()
-                .trim_start_matches("image")
-                .trim_end_matches(".png")
-                .parse::<u32>().unwrap();
-            let b_num = b.file_name().unwrap().to_str().unwrap()
-                .trim_start_matches("image")
-                .trim_end_matches(".png")
-                .parse::<u32>().unwrap();
-            a_num.cmp(&b_num)
-        });
-
-        let frames: Vec<_> = sorted_entries
-            .par_iter()
+        Ok(Video {
+            width,
+            height,
+            frame_rate: options.frame_rate(frame_rate),
+            frames: decode_frames(&filename, width, height, capacity, options, frame_rate, &tools.ffmpeg)?,
+            monitor: options.monitor.clone(),
+        })
+    }
+
+    /// Private function; the fallback for `from_file` that extracts every frame into a temporary `Workspace`.
+    fn from_images(filename: String, options: &LoadOptions, ffmpeg: &Path) -> Result<Video, RsframeError> {
+        options.validate(FrameRate::default())?;
+        let workspace = Workspace::new()?;
+
+        let video = Video::read_frames(&filename, workspace.path(), options, ffmpeg)?;
+        workspace.close()?;
+        Ok(video)
+    }
+
+    /// Private function; extracts the frames of `filename` picked by `options` into `temp` and loads them back in order.
+    fn read_frames(filename: &str, temp: &Path, options: &LoadOptions, ffmpeg: &Path) -> Result<Video, RsframeError> {
+        let frame_rate = FrameRate::default();
+        options.monitor.cancel.check()?;
+        run(command(ffmpeg)
+            .args(options.input_args(frame_rate))
+            .arg("-i")
+            .arg(filename)
+            .args(options.output_args(frame_rate))
+            .arg(temp.join("image%d.png")))?;
+
+        let mut entries: Vec<(u32, PathBuf)> = fs::read_dir(temp)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
             .filter_map(|path| {
-                let frame_path = path.to_str().unwrap().to_string();
-                Frame::from_img(frame_path).ok()
+                // Extract frame numbers from filenames so they can be sorted numerically
+                let frame_number = path.file_name()?.to_str()?
+                    .trim_start_matches("image")
+                    .trim_end_matches(".png")
+                    .parse::<u32>().ok()?;
+                Some((frame_number, path))
             })
             .collect();
-
-        if frames.is_empty() {
-            drop_folder(temp);
-            return Err("No frames were successfully loaded.".to_string());
-        }
-
-        let first_frame = &frames[0];
-        let video = Video {
+        entries.sort_by_key(|(frame_number, _)| *frame_number);
+
+        let frames = options.monitor.run("Loading", Some(entries.len() as u64), || {
+            entries
+                .par_iter()
+                .map(|(_, path)| {
+                    let frame = Frame::from_img(path.to_string_lossy().to_string())?;
+                    options.monitor.step()?;
+                    Ok(frame)
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })?;
+
+        let first_frame = frames.first().ok_or(RsframeError::NoFrames)?;
+        Ok(Video {
             width: first_frame.width,
             height: first_frame.height,
+            frame_rate: options.frame_rate(frame_rate),
             frames,
-        };
+            monitor: options.monitor.clone(),
+        })
+    }
 
-        drop_folder(temp);
+    /// Reads every frame of a `FrameSource` into a new Video, such as part of a `FileSource` that fits in memory.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `source` - The frames to read.
+    /// 
+    /// # Returns
+    /// 
+    /// A `Result` containing the new `Video`, or a `DimensionMismatch` error if a frame is not the size of the source.
+    pub fn from_source<S: FrameSource + ?Sized>(source: &S) -> Result<Video, RsframeError> {
+        let mut video = Video::new(source.width(), source.height());
+        video.frame_rate = source.frame_rate();
+        video.frames.reserve(source.len());
 
+        for frame in source.frames() {
+            video.append_frame(frame?)?;
+        }
         Ok(video)
     }
 
+    /// Sets where bulk effects, such as `bulk_draw_over` and `fade_in`, report their progress.
+    /// Effects that return a `Result` stop with a `Cancelled` error once the monitor's token is cancelled,
+    /// leaving the frames they didn't reach unchanged.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `monitor` - The monitor to report into, such as `Monitor::indicatif()`.
+    pub fn set_monitor(&mut self, monitor: Monitor) {
+        self.monitor = monitor;
+    }
+
     /// Makes all the frames monochrome.
     pub fn monochrome(&mut self) {
+        let progress = &self.monitor.progress;
+        progress.start("Monochrome", Some(self.frames.len() as u64));
         for frame in &mut self.frames {
             frame.monochrome();
+            progress.advance(1);
         }
+        progress.finish();
     }
 
     /// Returns the number of frames in the video.
@@ -460,32 +1020,307 @@ impl Video {
         self.frames.len()
     }
 
+    /// Private function; checks that a frame is the same size as the video.
+    fn check_frame(&self, frame: &Frame) -> Result<(), RsframeError> {
+        if frame.width == self.width && frame.height == self.height {
+            Ok(())
+        } else {
+            Err(RsframeError::dimensions(self.width, self.height, frame.width, frame.height))
+        }
+    }
+
+    /// Private function; checks that `start..=end` is a valid range of frames.
+    fn check_range(&self, start: usize, end: usize) -> Result<(), RsframeError> {
+        if start > end || end >= self.length() {
+            Err(RsframeError::InvalidRange { start, end, length: self.length() })
+        } else {
+            Ok(())
+        }
+    }
+
     /// Appends a frame to the video.
     /// 
     /// # Arguments
     /// 
     /// * `frame` - The frame to be added to the video.
-    pub fn append_frame(&mut self, frame: Frame) {
-        if frame.width == self.width && frame.height == self.height {
-            self.frames.push(frame);
-        } else {
-            panic!("Frame width or size does not match the video size\nFrame: {}x{}\nVideo: {}x{}", frame.width, frame.height, self.width, self.height);
-        }
+    /// 
+    /// # Returns
+    /// 
+    /// A `DimensionMismatch` error if the frame is not the same size as the video.
+    pub fn append_frame(&mut self, frame: Frame) -> Result<(), RsframeError> {
+        self.check_frame(&frame)?;
+        self.frames.push(frame);
+        Ok(())
     }
 
-    /// Appends multiple frames to the video.
+    /// Appends multiple frames to the video. Nothing is appended if any of the frames has the wrong size.
     /// 
     /// # Arguments
     /// 
     /// * `frames` - A box of frames to be added to the video.
-    pub fn bulk_append_frame(&mut self, frames: Box<[Frame]>) {
+    /// 
+    /// # Returns
+    /// 
+    /// A `DimensionMismatch` error if any frame is not the same size as the video.
+    #[allow(clippy::boxed_local)]
+    pub fn bulk_append_frame(&mut self, frames: Box<[Frame]>) -> Result<(), RsframeError> {
         for frame in frames.iter() {
-            if frame.width == self.width && frame.height == self.height {
-                self.frames.push(frame.clone());
-            } else {
-                panic!("Frame width or size does not match the video size\nFrame: {}x{}\nVideo: {}x{}", frame.width, frame.height, self.width, self.height);
-            }
+            self.check_frame(frame)?;
         }
+
+        self.frames.extend(frames.into_vec());
+        Ok(())
+    }
+
+    /// Scales every frame of the video to a new resolution with the given filter. The aspect ratio is not kept.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `width` - The new width of the video.
+    /// * `height` - The new height of the video.
+    /// * `filter` - The filter to scale with, such as `Filter::Lanczos3`.
+    /// 
+    /// # Returns
+    /// 
+    /// `Cancelled` if the monitor was cancelled, in which case the video is left as it was.
+    pub fn resize(&mut self, width: usize, height: usize, filter: Filter) -> Result<(), RsframeError> {
+        self.resize_with(width, height, &ResizeOptions::new().filter(filter))
+    }
+
+    /// Scales every frame of the video to a new resolution, with options such as gamma-correct filtering. Frames are scaled in parallel.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `width` - The new width of the video.
+    /// * `height` - The new height of the video.
+    /// * `options` - The filter and colour handling to scale with.
+    /// 
+    /// # Returns
+    /// 
+    /// `Cancelled` if the monitor was cancelled, in which case the video is left as it was.
+    pub fn resize_with(&mut self, width: usize, height: usize, options: &ResizeOptions) -> Result<(), RsframeError> {
+        // Frames are scaled into a new list, so a cancelled resize leaves the video as it was
+        self.frames = self.monitor.run("Resizing", Some(self.frames.len() as u64), || {
+            self.frames.par_iter().map(|frame| {
+                let pixels = resize::resample(&frame.pixels, frame.width, frame.height, width, height, options);
+                self.monitor.step()?;
+                Ok(Frame { pixels, width, height })
+            }).collect()
+        })?;
+
+        self.width = width;
+        self.height = height;
+        Ok(())
+    }
+
+    /// Makes every frame of the video fill a new resolution, by scaling it and then padding or cropping it.
+    /// Converting 16:9 footage to 9:16 is `fit(1080, 1920, FitMode::Cover, Anchor::CENTER, Pixel::black())`.
+    /// Frames are scaled with `Filter::Lanczos3`; use `fit_with` to pick another filter.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `target_width` - The width of the new video.
+    /// * `target_height` - The height of the new video.
+    /// * `mode` - Whether to fit the frames inside (`Contain`), fill and crop (`Cover`), `Stretch` them or only `Pad` them.
+    /// * `anchor` - Which part of the frames stays in view, such as `Anchor::CENTER` or `Anchor::TOP`.
+    /// * `background` - The colour of the padding.
+    /// 
+    /// # Returns
+    /// 
+    /// `Cancelled` if the monitor was cancelled, in which case the video is left as it was.
+    pub fn fit(&mut self, target_width: usize, target_height: usize, mode: FitMode, anchor: Anchor, background: Pixel) -> Result<(), RsframeError> {
+        self.fit_with(target_width, target_height, mode, anchor, background, &ResizeOptions::new())
+    }
+
+    /// Does the same thing as `fit`, but scales the frames with the given options. Frames are fitted in parallel.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `target_width` - The width of the new video.
+    /// * `target_height` - The height of the new video.
+    /// * `mode` - Whether to fit the frames inside (`Contain`), fill and crop (`Cover`), `Stretch` them or only `Pad` them.
+    /// * `anchor` - Which part of the frames stays in view, such as `Anchor::CENTER` or `Anchor::TOP`.
+    /// * `background` - The colour of the padding.
+    /// * `options` - The filter and colour handling to scale with.
+    /// 
+    /// # Returns
+    /// 
+    /// `Cancelled` if the monitor was cancelled, in which case the video is left as it was.
+    pub fn fit_with(
+        &mut self,
+        target_width: usize,
+        target_height: usize,
+        mode: FitMode,
+        anchor: Anchor,
+        background: Pixel,
+        options: &ResizeOptions,
+    ) -> Result<(), RsframeError> {
+        self.frames = self.monitor.run("Fitting", Some(self.frames.len() as u64), || {
+            self.frames.par_iter().map(|frame| {
+                let frame = frame.fitted(target_width, target_height, mode, anchor, background, options);
+                self.monitor.step()?;
+                Ok(frame)
+            }).collect()
+        })?;
+
+        self.width = target_width;
+        self.height = target_height;
+        Ok(())
+    }
+
+    /// Changes the orientation of every frame, such as turning footage that was filmed sideways upright.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `orientation` - The rotation or flip to do.
+    /// 
+    /// # Returns
+    /// 
+    /// `Cancelled` if the monitor was cancelled, in which case the video is left as it was.
+    pub fn orient(&mut self, orientation: Orientation) -> Result<(), RsframeError> {
+        self.frames = self.monitor.run("Rotating", Some(self.frames.len() as u64), || {
+            self.frames.par_iter().map(|frame| {
+                let pixels = transform::orient(&frame.pixels, frame.width, frame.height, orientation);
+                let (width, height) = if orientation.swaps_dimensions() { (frame.height, frame.width) } else { (frame.width, frame.height) };
+                self.monitor.step()?;
+                Ok(Frame { pixels, width, height })
+            }).collect()
+        })?;
+
+        if orientation.swaps_dimensions() {
+            std::mem::swap(&mut self.width, &mut self.height);
+        }
+        Ok(())
+    }
+
+    /// Rotates, scales, skews or moves the contents of every frame. The video keeps its size.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `affine` - The transform to do, in pixels from the top left corner of the frames.
+    /// * `filter` - The filter to sample the frames with, such as `Filter::Bilinear` or `Filter::Bicubic`.
+    /// * `fill` - The colour of the areas nothing is moved into.
+    /// 
+    /// # Returns
+    /// 
+    /// `Cancelled` if the monitor was cancelled, in which case the video is left as it was.
+    pub fn transform(&mut self, affine: &Affine, filter: Filter, fill: Pixel) -> Result<(), RsframeError> {
+        self.frames = self.monitor.run("Transforming", Some(self.frames.len() as u64), || {
+            self.frames.par_iter().map(|frame| {
+                let pixels = transform::warp(&frame.pixels, frame.width, frame.height, affine, filter, fill);
+                self.monitor.step()?;
+                Ok(Frame { pixels, width: frame.width, height: frame.height })
+            }).collect()
+        })?;
+        Ok(())
+    }
+
+    /// Rotates the contents of every frame clockwise around its middle by any angle. The video keeps its size, so the corners are cut off.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `degrees` - The angle to rotate by.
+    /// * `filter` - The filter to sample the frames with, such as `Filter::Bilinear` or `Filter::Bicubic`.
+    /// * `fill` - The colour of the areas nothing is rotated into.
+    /// 
+    /// # Returns
+    /// 
+    /// `Cancelled` if the monitor was cancelled, in which case the video is left as it was.
+    pub fn rotate(&mut self, degrees: f64, filter: Filter, fill: Pixel) -> Result<(), RsframeError> {
+        let affine = Affine::rotation(degrees).around(self.width as f64 / 2.0, self.height as f64 / 2.0);
+        self.transform(&affine, filter, fill)
+    }
+
+    /// Colour corrects every frame of the video, applying each adjustment in turn.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `adjustments` - The adjustments to apply, such as `[Adjustment::Exposure(0.5), Adjustment::Vibrance(0.3)]`.
+    /// 
+    /// # Returns
+    /// 
+    /// `Cancelled` if the monitor was cancelled.
+    pub fn adjust(&mut self, adjustments: &[Adjustment]) -> Result<(), RsframeError> {
+        if self.frames.is_empty() {
+            return Ok(());
+        }
+        self.adjust_range(adjustments, 0, self.frames.len() - 1)
+    }
+
+    /// Colour corrects a range of frames in the video, applying each adjustment in turn.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `adjustments` - The adjustments to apply.
+    /// * `start_frame` - The starting index of the frames to adjust.
+    /// * `end_frame` - The ending index of the frames to adjust.
+    /// 
+    /// # Returns
+    /// 
+    /// An `InvalidRange` error if the range is outside of the video, or `Cancelled` if the monitor was cancelled.
+    pub fn adjust_range(&mut self, adjustments: &[Adjustment], start_frame: usize, end_frame: usize) -> Result<(), RsframeError> {
+        self.check_range(start_frame, end_frame)?;
+
+        self.monitor.run("Adjusting", Some((end_frame - start_frame + 1) as u64), || {
+            for frame in &mut self.frames[start_frame..=end_frame] {
+                frame.adjust(adjustments);
+                self.monitor.step()?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Grades every frame of the video with a LUT, such as a look loaded with `Lut3D::from_cube_file`.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `lut` - The LUT to look colours up in.
+    /// * `interpolation` - How colours between the entries of the LUT are worked out, such as `Interpolation::Tetrahedral`.
+    /// * `intensity` - How much of the look to use, from 0 (none) to 1 (all of it).
+    /// 
+    /// # Returns
+    /// 
+    /// `Cancelled` if the monitor was cancelled.
+    pub fn apply_lut(&mut self, lut: &Lut3D, interpolation: Interpolation, intensity: f32) -> Result<(), RsframeError> {
+        self.monitor.run("Grading", Some(self.frames.len() as u64), || {
+            for frame in &mut self.frames {
+                frame.apply_lut(lut, interpolation, intensity);
+                self.monitor.step()?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Turns every frame of the video into greys of the same brightness.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `luma` - How the brightness of each pixel is worked out, such as `Luma::Rec709`.
+    /// 
+    /// # Returns
+    /// 
+    /// `Cancelled` if the monitor was cancelled.
+    pub fn greyscale(&mut self, luma: Luma) -> Result<(), RsframeError> {
+        self.apply_matrix(&ColorMatrix::greyscale(luma))
+    }
+
+    /// Recolours every frame of the video with a colour matrix, such as `ColorMatrix::sepia()`.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `matrix` - The matrix that works out the new channels of every pixel from its old ones.
+    /// 
+    /// # Returns
+    /// 
+    /// `Cancelled` if the monitor was cancelled.
+    pub fn apply_matrix(&mut self, matrix: &ColorMatrix) -> Result<(), RsframeError> {
+        self.monitor.run("Recolouring", Some(self.frames.len() as u64), || {
+            for frame in &mut self.frames {
+                frame.apply_matrix(matrix);
+                self.monitor.step()?;
+            }
+            Ok(())
+        })
     }
 
     /// Crops the video to the specified dimensions.
@@ -496,18 +1331,23 @@ impl Video {
     /// * `y_start` - The y-coordinate to start cropping from.
     /// * `crop_width` - The width of the cropped area.
     /// * `crop_height` - The height of the cropped area.
-    pub fn crop(&mut self, x_start: usize, y_start: usize, crop_width: usize, crop_height: usize) {
-        // Validate crop boundaries
-        if x_start + crop_width > self.width || y_start + crop_height > self.height {
-            panic!("Crop dimensions exceed video boundaries");
-        }
+    /// 
+    /// # Returns
+    /// 
+    /// An `InvalidRange` error if the cropped area does not fit inside the video.
+    pub fn crop(&mut self, x_start: usize, y_start: usize, crop_width: usize, crop_height: usize) -> Result<(), RsframeError> {
+        // Validate crop boundaries, without overflowing on huge sizes
+        let x_end = x_start.checked_add(crop_width).filter(|&end| end <= self.width)
+            .ok_or(RsframeError::InvalidRange { start: x_start, end: x_start.saturating_add(crop_width), length: self.width })?;
+        let y_end = y_start.checked_add(crop_height).filter(|&end| end <= self.height)
+            .ok_or(RsframeError::InvalidRange { start: y_start, end: y_start.saturating_add(crop_height), length: self.height })?;
 
         // Modify each frame to create a cropped version
         self.frames = self.frames.iter_mut().map(|frame| {
             let mut cropped_pixels = Vec::with_capacity(crop_width * crop_height);
 
-            for y in y_start..(y_start + crop_height) {
-                for x in x_start..(x_start + crop_width) {
+            for y in y_start..y_end {
+                for x in x_start..x_end {
                     cropped_pixels.push(frame.get_pixel(x, y));
                 }
             }
@@ -522,6 +1362,7 @@ impl Video {
         // Update video dimensions
         self.width = crop_width;
         self.height = crop_height;
+        Ok(())
     }
 
     /// Appends a still frame to the video a specified number of times.
@@ -530,14 +1371,17 @@ impl Video {
     /// 
     /// * `frame` - The frame to be added.
     /// * `amount` - The number of times to add the frame.
-    pub fn append_still(&mut self, frame: Frame, amount: usize) {
-        if frame.width == self.width && frame.height == self.height {
-            for _i in 0..amount {
-                self.frames.push(frame.clone());
-            }
-        } else {
-            panic!("Frame width or size does not match the video size\nFrame: {}x{}\nVideo: {}x{}", frame.width, frame.height, self.width, self.height);
+    /// 
+    /// # Returns
+    /// 
+    /// A `DimensionMismatch` error if the frame is not the same size as the video.
+    pub fn append_still(&mut self, frame: Frame, amount: usize) -> Result<(), RsframeError> {
+        self.check_frame(&frame)?;
+
+        for _i in 0..amount {
+            self.frames.push(frame.clone());
         }
+        Ok(())
     }
 
     /// Applies a fade-in effect to the video.
@@ -547,17 +1391,29 @@ impl Video {
     /// * `frame_duration` - The duration of the fade-in effect in frames.
     /// * `color` - The colour to fade in.
     /// * `position` - The position to apply the fade-in effect (start or end).
-    pub fn fade_in(&mut self, frame_duration: usize, color: Pixel, position: VideoPosition) {
+    /// 
+    /// # Returns
+    /// 
+    /// An `InvalidRange` error if the fade is longer than the video, or `Cancelled` if the monitor was cancelled.
+    pub fn fade_in(&mut self, frame_duration: usize, color: Pixel, position: VideoPosition) -> Result<(), RsframeError> {
+        if frame_duration > self.length() {
+            return Err(RsframeError::InvalidRange { start: 0, end: frame_duration, length: self.length() });
+        }
+
         let frame_indices: Vec<usize> = match position {
             VideoPosition::START => (0..frame_duration).rev().collect(),
             VideoPosition::END => ((self.length() - frame_duration)..self.length()).collect(),
         };
 
-        for (i, &frame_index) in frame_indices.iter().enumerate() {
-            if let Some(frame) = self.frames.get_mut(frame_index) {
-                frame.tint(color, i as f32 / frame_duration as f32);
+        self.monitor.run("Fading", Some(frame_duration as u64), || {
+            for (i, &frame_index) in frame_indices.iter().enumerate() {
+                if let Some(frame) = self.frames.get_mut(frame_index) {
+                    frame.tint(color, i as f32 / frame_duration as f32);
+                }
+                self.monitor.step()?;
             }
-        }
+            Ok(())
+        })
     }
 
     /// Splices the video to keep only the frames in the specified range.
@@ -566,70 +1422,79 @@ impl Video {
     /// 
     /// * `start` - The starting index of the splice.
     /// * `end` - The ending index of the splice.
-    pub fn splice(&mut self, start: usize, end: usize) {
-        if start >= self.length() || end >= self.length() || start > end {
-            panic!(
-                "Invalid range for splicing: start={} end={} length={}",
-                start, end, self.length()
-            );
-        }
+    /// 
+    /// # Returns
+    /// 
+    /// An `InvalidRange` error if the range is outside of the video.
+    pub fn splice(&mut self, start: usize, end: usize) -> Result<(), RsframeError> {
+        self.check_range(start, end)?;
 
         self.frames = self.frames[start..=end].to_vec();
+        Ok(())
     }
 
-    /// Concatenates another video to the current video.
+    /// Concatenates another video to the current video. The result keeps this video's frame rate.
     /// 
     /// # Arguments
     /// 
     /// * `other_video` - The video to be concatenated.
-    pub fn concat(&mut self, other_video: Video) {
-        for frame in other_video.frames {
-            self.frames.push(frame.clone());
+    /// 
+    /// # Returns
+    /// 
+    /// A `DimensionMismatch` error if the other video is not the same size as this one.
+    pub fn concat(&mut self, other_video: Video) -> Result<(), RsframeError> {
+        if other_video.width != self.width || other_video.height != self.height {
+            return Err(RsframeError::dimensions(self.width, self.height, other_video.width, other_video.height));
         }
+
+        self.frames.extend(other_video.frames);
+        Ok(())
     }
 
-    /// Saves the video to the specified location with the given frames per second (fps).
+    /// Saves the video to the specified location at its `frame_rate`, unless `options` sets a different one.
+    /// Frames are streamed into FFmpeg while it encodes, so rendering needs no scratch disk space.
     /// 
     /// # Arguments
     /// 
     /// * `export_location` - The path where the video will be saved.
-    /// * `fps` - The frames per second for the output video.
-    pub fn save(&self, export_location: String, fps: u8, keep_folder: bool, ffmpeg: &str) {
-        let temporary = create_tmp_folder();
+    /// * `options` - The codec, quality and container to encode with, such as `EncodeOptions::default()`.
+    /// * `tools` - The tools to encode with, such as `Tools::global()`.
+    /// 
+    /// # Returns
+    /// 
+    /// A `Result` indicating success or failure of the render, or `UnsupportedEncoder` if ffmpeg lacks the codec.
+    /// Lossless options with a codec that has no lossless mode return `UnsupportedLossless`.
+    pub fn save(&self, export_location: String, options: &EncodeOptions, tools: &Tools) -> Result<(), RsframeError> {
+        save_source(self, &export_location, options, tools)
+    }
 
-        let progress_bar = indicatif::ProgressBar::new(self.frames.len() as u64);
-        progress_bar.set_style(indicatif::ProgressStyle::default_bar()
-            .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} frames")
-            .unwrap()
-            .progress_chars("=> "));
+    /// Writes every frame as a BMP image into a new temporary `Workspace`, named `image1.bmp`, `image2.bmp`, ...
+    /// This is useful for finding out what went wrong in a render and at what time. The folder is removed when the
+    /// workspace is dropped, so call `keep` on it to look at the images afterwards.
+    /// 
+    /// # Returns
+    /// 
+    /// A `Result` containing the `Workspace` with the images.
+    pub fn dump_frames(&self) -> Result<Workspace, RsframeError> {
+        let workspace = Workspace::new()?;
+        self.write_frames(workspace.path())?;
+        Ok(workspace)
+    }
 
+    /// Private function; writes every frame into `folder` as `image1.bmp`, `image2.bmp`, ...
+    fn write_frames(&self, folder: &Path) -> Result<(), RsframeError> {
         // Use Rayon to parallelize the loop
-        self.frames.par_iter().enumerate().for_each(|(fi, frame)| {
+        self.frames.par_iter().enumerate().try_for_each(|(fi, frame)| {
             let mut img = RgbImage::new(frame.width as u32, frame.height as u32);
             for (i, pixel) in frame.pixels.iter().enumerate() {
                 let y = i / frame.width;
                 let x = i % frame.width;
 
-                img.put_pixel(x as u32, y as u32, Rgb([pixel.r, pixel.g, pixel.b]));
+                img.put_pixel(x as u32, y as u32, Rgb(pixel.flatten()));
             }
-            img.save(format!("{}/image{}.bmp", temporary, fi + 1)).unwrap();
-            progress_bar.inc(1);
-        });
-
-        progress_bar.finish();
-        println!("Starting build!");
-        let results = build_folder(temporary.clone(), fps as i32, export_location, ffmpeg);
-
-        match results {
-            Ok(_) => {
-                if !keep_folder {
-                    drop_folder(temporary);
-                }
-            }
-            Err(_) => {
-                println!("Cannot render video.");
-            }
-        }
+            img.save(folder.join(format!("image{}.bmp", fi + 1)))?;
+            Ok(())
+        })
     }
 
     /// Retrieves a reference to a specific frame in the video.
@@ -667,6 +1532,13 @@ impl Video {
     /// * `y_offset` - The y-coordinate offset for the overlay.
     /// * `start_frame` - The starting index of the frames to overlay on.
     /// * `end_frame` - The ending index of the frames to overlay on.
+    /// * `opacity` - The opacity of the whole overlay, from 0 (invisible) to 1 (as opaque as its pixels).
+    /// * `mode` - How the colours of the overlay combine with the frames, such as `BlendMode::Normal` or `BlendMode::Screen`.
+    /// 
+    /// # Returns
+    /// 
+    /// An `InvalidRange` error if the range is outside of the video, or `Cancelled` if the monitor was cancelled.
+    #[allow(clippy::too_many_arguments)]
     pub fn bulk_draw_over(
         &mut self,
         overlay_frame: &Frame,
@@ -674,17 +1546,18 @@ impl Video {
         y_offset: usize,
         start_frame: usize,
         end_frame: usize,
-    ) {
-        if start_frame > end_frame || end_frame >= self.length() {
-            panic!(
-                "Invalid range: start_frame={} end_frame={} length={}",
-                start_frame, end_frame, self.length()
-            );
-        }
-
-        for i in start_frame..=end_frame {
-            self.frames[i].draw_over(overlay_frame, x_offset, y_offset);
-        }
+        opacity: f32,
+        mode: BlendMode,
+    ) -> Result<(), RsframeError> {
+        self.check_range(start_frame, end_frame)?;
+
+        self.monitor.run("Drawing", Some((end_frame - start_frame + 1) as u64), || {
+            for i in start_frame..=end_frame {
+                self.frames[i].draw_over(overlay_frame, x_offset, y_offset, opacity, mode);
+                self.monitor.step()?;
+            }
+            Ok(())
+        })
     }
 
     /// Draws an overlay frame over a range of frames in the video using chroma keying.
@@ -698,6 +1571,11 @@ impl Video {
     /// * `start_frame` - The starting index of the frames to overlay on.
     /// * `end_frame` - The ending index of the frames to overlay on.
     /// * `tolerance` - The tolerance level for the chroma keying effect.
+    /// 
+    /// # Returns
+    /// 
+    /// An `InvalidRange` error if the range is outside of the video, or `Cancelled` if the monitor was cancelled.
+    #[allow(clippy::too_many_arguments)]
     pub fn bulk_draw_with_chroma_key(
         &mut self,
         overlay_frame: &Frame,
@@ -707,16 +1585,116 @@ impl Video {
         start_frame: usize,
         end_frame: usize,
         tolerance: u8
-    ) {
-        if start_frame > end_frame || end_frame >= self.length() {
-            panic!(
-                "Invalid range: start_frame={} end_frame={} length={}",
-                start_frame, end_frame, self.length()
-            );
-        }
+    ) -> Result<(), RsframeError> {
+        self.bulk_draw_keyed(overlay_frame, x_offset, y_offset, &exact_key(chroma_key, tolerance), start_frame, end_frame)
+    }
 
-        for i in start_frame..=end_frame {
-            self.frames[i].draw_with_chroma_key(overlay_frame, x_offset, y_offset, chroma_key, tolerance);
+    /// Draws an overlay frame, keyed with a keyer, over a range of frames in the video.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `overlay_frame` - The frame to key and overlay.
+    /// * `x_offset` - The x-coordinate offset for the overlay.
+    /// * `y_offset` - The y-coordinate offset for the overlay.
+    /// * `key` - The keyer that removes the screen from the overlay.
+    /// * `start_frame` - The starting index of the frames to overlay on.
+    /// * `end_frame` - The ending index of the frames to overlay on.
+    /// 
+    /// # Returns
+    /// 
+    /// An `InvalidRange` error if the range is outside of the video, or `Cancelled` if the monitor was cancelled.
+    pub fn bulk_draw_keyed(
+        &mut self,
+        overlay_frame: &Frame,
+        x_offset: usize,
+        y_offset: usize,
+        key: &ChromaKey,
+        start_frame: usize,
+        end_frame: usize
+    ) -> Result<(), RsframeError> {
+        self.check_range(start_frame, end_frame)?;
+
+        // Key the overlay once instead of for every frame
+        let mut keyed = overlay_frame.clone();
+        keyed.chroma_key(key);
+        self.monitor.run("Drawing", Some((end_frame - start_frame + 1) as u64), || {
+            for i in start_frame..=end_frame {
+                self.frames[i].draw_over(&keyed, x_offset, y_offset, 1.0, BlendMode::Normal);
+                self.monitor.step()?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Removes a green or blue screen from every frame of the video, leaving the foreground on a transparent background.
+    /// The keyed frames can then be drawn over a background with `Frame::draw_over`.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `key` - The keyer, such as `ChromaKey::new(Pixel::new(70, 180, 80))`.
+    /// 
+    /// # Returns
+    /// 
+    /// `Cancelled` if the monitor was cancelled.
+    pub fn chroma_key(&mut self, key: &ChromaKey) -> Result<(), RsframeError> {
+        self.monitor.run("Keying", Some(self.frames.len() as u64), || {
+            for frame in &mut self.frames {
+                frame.chroma_key(key);
+                self.monitor.step()?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Replaces every frame of the video with the matte a keyer makes of it, for checking the key over the whole clip.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `key` - The keyer to make the mattes with.
+    /// 
+    /// # Returns
+    /// 
+    /// `Cancelled` if the monitor was cancelled, in which case the video is left unchanged.
+    pub fn matte(&mut self, key: &ChromaKey) -> Result<(), RsframeError> {
+        let mut mattes = Vec::with_capacity(self.frames.len());
+        self.monitor.run("Keying", Some(self.frames.len() as u64), || {
+            for frame in &self.frames {
+                mattes.push(frame.matte(key));
+                self.monitor.step()?;
+            }
+            Ok(())
+        })?;
+        self.frames = mattes;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_fractional_frame_rates() {
+        assert_eq!(FrameRate::parse("30000/1001"), Some(FrameRate { numerator: 30000, denominator: 1001 }));
+        assert_eq!(FrameRate::parse(" 50/2 "), Some(FrameRate::from_fps(25)));
+    }
+
+    #[test]
+    fn parses_whole_and_decimal_frame_rates() {
+        assert_eq!(FrameRate::parse("24"), Some(FrameRate::from_fps(24)));
+        assert_eq!(FrameRate::parse("29.97"), Some(FrameRate::new(2997, 100)));
+        assert_eq!(FrameRate::parse("23.976").map(|rate| rate.as_f64()), Some(23.976));
+    }
+
+    #[test]
+    fn rejects_invalid_frame_rates() {
+        for text in ["", "0", "0/1", "0/0", "-5", "abc", "30/x", "NaN", "inf"] {
+            assert_eq!(FrameRate::parse(text), None, "{:?}", text);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn zero_denominators_are_treated_as_one() {
+        assert_eq!(FrameRate::new(30, 0), FrameRate::from_fps(30));
+    }
+}